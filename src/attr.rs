@@ -0,0 +1,323 @@
+use serde_json::Value;
+use std::fmt;
+use std::marker::PhantomData;
+
+use crate::{Filter, Order, QueryVector, RankBy, Timestamp, Vector};
+
+/// A typed handle to an attribute, for building filters and rankings whose
+/// values are checked against the attribute's type at compile time.
+///
+/// ```
+/// use rs_puff::{Attr, Filter, Timestamp, Vector};
+///
+/// const AGE: Attr<u64> = Attr::new("age");
+/// const TAGS: Attr<Vec<String>> = Attr::new("tags");
+/// const TITLE: Attr<String> = Attr::new("title");
+/// const CREATED_AT: Attr<Timestamp> = Attr::new("created_at");
+/// const EMBEDDING: Attr<Vector> = Attr::new("embedding");
+///
+/// let filter = AGE.gte(18) & TAGS.contains("rust") & TITLE.glob("Intro*");
+/// assert_eq!(filter, Filter::gte("age", 18) & Filter::contains("tags", "rust") & Filter::glob("title", "Intro*"));
+///
+/// let rank_by = TITLE.bm25("query");
+/// let rank_by = CREATED_AT.desc();
+/// let rank_by = EMBEDDING.ann(vec![0.1, 0.2, 0.3]);
+/// ```
+///
+/// Operators that don't apply to the attribute's type aren't available:
+///
+/// ```compile_fail
+/// use rs_puff::Attr;
+///
+/// const TITLE: Attr<String> = Attr::new("title");
+/// TITLE.gte(18);
+/// ```
+///
+/// ```compile_fail
+/// use rs_puff::Attr;
+///
+/// const AGE: Attr<u64> = Attr::new("age");
+/// AGE.glob("1*");
+/// ```
+pub struct Attr<T> {
+    name: &'static str,
+    _type: PhantomData<fn() -> T>,
+}
+
+impl<T> Attr<T> {
+    pub const fn new(name: &'static str) -> Self {
+        Attr { name, _type: PhantomData }
+    }
+
+    pub const fn name(&self) -> &'static str {
+        self.name
+    }
+}
+
+impl<T> Clone for Attr<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Attr<T> {}
+
+impl<T> fmt::Debug for Attr<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Attr").field(&self.name).finish()
+    }
+}
+
+/// A scalar attribute type, supporting equality and `In` filters.
+pub trait AttrType: Sized {
+    fn into_value(self) -> Value;
+}
+
+/// A scalar attribute type that supports range filters and ordering.
+pub trait Ordered: AttrType {}
+
+/// A value accepted where an attribute of type `T` is expected, e.g. `&str`
+/// for `Attr<String>`.
+pub trait IntoAttrValue<T> {
+    fn into_attr_value(self) -> T;
+}
+
+impl<T: AttrType> IntoAttrValue<T> for T {
+    fn into_attr_value(self) -> T {
+        self
+    }
+}
+
+impl IntoAttrValue<String> for &str {
+    fn into_attr_value(self) -> String {
+        self.to_string()
+    }
+}
+
+macro_rules! impl_attr_type {
+    ($($ty:ty),* $(,)?) => {
+        $(
+            impl AttrType for $ty {
+                fn into_value(self) -> Value {
+                    self.into()
+                }
+            }
+        )*
+    };
+}
+
+impl_attr_type!(u64, i64, f64, bool, String, Timestamp);
+
+impl Ordered for u64 {}
+impl Ordered for i64 {}
+impl Ordered for f64 {}
+impl Ordered for String {}
+impl Ordered for Timestamp {}
+
+#[cfg(feature = "uuid")]
+impl AttrType for uuid::Uuid {
+    fn into_value(self) -> Value {
+        Value::String(self.to_string())
+    }
+}
+
+#[cfg(feature = "uuid")]
+impl Ordered for uuid::Uuid {}
+
+fn value<T: AttrType>(v: impl IntoAttrValue<T>) -> Value {
+    v.into_attr_value().into_value()
+}
+
+fn values<T: AttrType>(vs: impl IntoIterator<Item = impl IntoAttrValue<T>>) -> Vec<Value> {
+    vs.into_iter().map(value).collect()
+}
+
+impl<T: AttrType> Attr<T> {
+    pub fn eq(&self, v: impl IntoAttrValue<T>) -> Filter {
+        Filter::eq(self.name, value(v))
+    }
+
+    pub fn not_eq(&self, v: impl IntoAttrValue<T>) -> Filter {
+        Filter::not_eq(self.name, value(v))
+    }
+
+    /// Matches documents where the attribute is null or missing.
+    pub fn is_null(&self) -> Filter {
+        Filter::eq(self.name, Value::Null)
+    }
+
+    pub fn r#in(&self, vs: impl IntoIterator<Item = impl IntoAttrValue<T>>) -> Filter {
+        Filter::r#in(self.name, values(vs))
+    }
+
+    pub fn not_in(&self, vs: impl IntoIterator<Item = impl IntoAttrValue<T>>) -> Filter {
+        Filter::not_in(self.name, values(vs))
+    }
+}
+
+impl<T: Ordered> Attr<T> {
+    pub fn lt(&self, v: impl IntoAttrValue<T>) -> Filter {
+        Filter::lt(self.name, value(v))
+    }
+
+    pub fn lte(&self, v: impl IntoAttrValue<T>) -> Filter {
+        Filter::lte(self.name, value(v))
+    }
+
+    pub fn gt(&self, v: impl IntoAttrValue<T>) -> Filter {
+        Filter::gt(self.name, value(v))
+    }
+
+    pub fn gte(&self, v: impl IntoAttrValue<T>) -> Filter {
+        Filter::gte(self.name, value(v))
+    }
+
+    pub fn asc(&self) -> RankBy {
+        RankBy::attribute(self.name, Order::Asc)
+    }
+
+    pub fn desc(&self) -> RankBy {
+        RankBy::attribute(self.name, Order::Desc)
+    }
+}
+
+impl Attr<String> {
+    pub fn glob(&self, pattern: impl Into<String>) -> Filter {
+        Filter::glob(self.name, pattern)
+    }
+
+    pub fn not_glob(&self, pattern: impl Into<String>) -> Filter {
+        Filter::not_glob(self.name, pattern)
+    }
+
+    pub fn iglob(&self, pattern: impl Into<String>) -> Filter {
+        Filter::iglob(self.name, pattern)
+    }
+
+    pub fn not_iglob(&self, pattern: impl Into<String>) -> Filter {
+        Filter::not_iglob(self.name, pattern)
+    }
+
+    pub fn regex(&self, pattern: impl Into<String>) -> Filter {
+        Filter::regex(self.name, pattern)
+    }
+
+    pub fn contains_all_tokens(&self, value: impl Into<String>) -> Filter {
+        Filter::contains_all_tokens(self.name, value)
+    }
+
+    pub fn contains_token_sequence(&self, value: impl Into<String>) -> Filter {
+        Filter::contains_token_sequence(self.name, value)
+    }
+
+    pub fn bm25(&self, query: impl Into<String>) -> RankBy {
+        RankBy::bm25(self.name, query)
+    }
+}
+
+impl<T: AttrType> Attr<Vec<T>> {
+    pub fn contains(&self, v: impl IntoAttrValue<T>) -> Filter {
+        Filter::contains(self.name, value(v))
+    }
+
+    pub fn not_contains(&self, v: impl IntoAttrValue<T>) -> Filter {
+        Filter::not_contains(self.name, value(v))
+    }
+
+    pub fn contains_any(&self, vs: impl IntoIterator<Item = impl IntoAttrValue<T>>) -> Filter {
+        Filter::contains_any(self.name, values(vs))
+    }
+
+    pub fn not_contains_any(&self, vs: impl IntoIterator<Item = impl IntoAttrValue<T>>) -> Filter {
+        Filter::not_contains_any(self.name, values(vs))
+    }
+}
+
+impl<T: Ordered> Attr<Vec<T>> {
+    pub fn any_lt(&self, v: impl IntoAttrValue<T>) -> Filter {
+        Filter::any_lt(self.name, value(v))
+    }
+
+    pub fn any_lte(&self, v: impl IntoAttrValue<T>) -> Filter {
+        Filter::any_lte(self.name, value(v))
+    }
+
+    pub fn any_gt(&self, v: impl IntoAttrValue<T>) -> Filter {
+        Filter::any_gt(self.name, value(v))
+    }
+
+    pub fn any_gte(&self, v: impl IntoAttrValue<T>) -> Filter {
+        Filter::any_gte(self.name, value(v))
+    }
+}
+
+impl Attr<Vector> {
+    /// Approximate nearest neighbor ranking, as `RankBy::vector`.
+    pub fn ann(&self, query: impl Into<QueryVector>) -> RankBy {
+        RankBy::vector(self.name, query)
+    }
+
+    /// Exact nearest neighbor ranking, as `RankBy::vector_knn`.
+    pub fn knn(&self, query: impl Into<QueryVector>) -> RankBy {
+        RankBy::vector_knn(self.name, query)
+    }
+}
+
+#[cfg(feature = "half")]
+impl Attr<crate::F16Vector> {
+    /// Approximate nearest neighbor ranking, as `RankBy::vector`.
+    pub fn ann(&self, query: impl Into<QueryVector>) -> RankBy {
+        RankBy::vector(self.name, query)
+    }
+
+    /// Exact nearest neighbor ranking, as `RankBy::vector_knn`.
+    pub fn knn(&self, query: impl Into<QueryVector>) -> RankBy {
+        RankBy::vector_knn(self.name, query)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const AGE: Attr<u64> = Attr::new("age");
+    const SCORE: Attr<f64> = Attr::new("score");
+    const NAME: Attr<String> = Attr::new("name");
+    const ACTIVE: Attr<bool> = Attr::new("active");
+    const TAGS: Attr<Vec<String>> = Attr::new("tags");
+    const SIZES: Attr<Vec<u64>> = Attr::new("sizes");
+
+    #[test]
+    fn test_scalar_filters() {
+        assert_eq!(AGE.gte(18), Filter::gte("age", 18));
+        assert_eq!(SCORE.lt(0.5), Filter::lt("score", 0.5));
+        assert_eq!(NAME.eq("alice"), Filter::eq("name", "alice"));
+        assert_eq!(NAME.eq(String::from("alice")), Filter::eq("name", "alice"));
+        assert_eq!(ACTIVE.not_eq(true), Filter::not_eq("active", true));
+        assert_eq!(AGE.is_null(), Filter::eq("age", Value::Null));
+        assert_eq!(AGE.r#in([1, 2]), Filter::r#in("age", vec![1.into(), 2.into()]));
+        assert_eq!(NAME.not_in(["a", "b"]), Filter::not_in("name", vec!["a".into(), "b".into()]));
+        assert_eq!(NAME.iglob("a*"), Filter::iglob("name", "a*"));
+    }
+
+    #[test]
+    fn test_array_filters() {
+        assert_eq!(TAGS.contains("rust"), Filter::contains("tags", "rust"));
+        assert_eq!(
+            TAGS.contains_any(["rust", "go"]),
+            Filter::contains_any("tags", vec!["rust".into(), "go".into()])
+        );
+        assert_eq!(SIZES.any_gt(10), Filter::any_gt("sizes", 10));
+    }
+
+    #[test]
+    fn test_rankings() {
+        const TS: Attr<Timestamp> = Attr::new("ts");
+        const EMBEDDING: Attr<Vector> = Attr::new("embedding");
+
+        assert_eq!(TS.desc(), RankBy::attribute("ts", Order::Desc));
+        assert_eq!(NAME.bm25("query"), RankBy::bm25("name", "query"));
+        assert_eq!(EMBEDDING.ann(vec![1.0, 2.0]), RankBy::vector("embedding", vec![1.0, 2.0]));
+        assert_eq!(TS.gt(Timestamp::new("2024-01-01T00:00:00Z")), Filter::gt("ts", "2024-01-01T00:00:00Z"));
+    }
+}
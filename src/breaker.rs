@@ -0,0 +1,329 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::{Error, Result};
+
+/// What a circuit breaker guards.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BreakerScope {
+    /// One breaker for every request the client makes.
+    #[default]
+    BaseUrl,
+    /// One breaker per namespace, plus one for requests outside a namespace.
+    Namespace,
+}
+
+/// Settings for the circuit breaker enabled with
+/// `Client::with_circuit_breaker`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CircuitBreakerConfig {
+    pub scope: BreakerScope,
+    /// Recent requests the failure rate is measured over.
+    pub window_size: usize,
+    /// Requests needed in the window before the breaker can open.
+    pub min_requests: usize,
+    /// The fraction of failed requests that opens the breaker.
+    pub failure_rate: f64,
+    /// Requests slower than this count as failures even if they succeed.
+    pub slow_call: Duration,
+    /// How long the breaker stays open before letting probes through.
+    pub open_duration: Duration,
+    /// Probe requests allowed at once while half-open.
+    pub half_open_requests: usize,
+}
+
+impl Default for CircuitBreakerConfig {
+    fn default() -> Self {
+        CircuitBreakerConfig {
+            scope: BreakerScope::BaseUrl,
+            window_size: 50,
+            min_requests: 20,
+            failure_rate: 0.5,
+            slow_call: Duration::from_secs(10),
+            open_duration: Duration::from_secs(30),
+            half_open_requests: 1,
+        }
+    }
+}
+
+/// The state of a circuit breaker, for health checks.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CircuitState {
+    /// Requests flow normally.
+    #[default]
+    Closed,
+    /// Requests fail fast with `Error::CircuitOpen`.
+    Open,
+    /// A few probe requests are let through; one success closes the breaker
+    /// and one failure reopens it.
+    HalfOpen,
+}
+
+#[derive(Debug, Default)]
+struct Circuit {
+    state: CircuitState,
+    /// Recent outcomes while closed; `true` is a failure.
+    outcomes: VecDeque<bool>,
+    opened: Option<Instant>,
+    probes: usize,
+}
+
+impl Circuit {
+    fn open(&mut self) {
+        self.state = CircuitState::Open;
+        self.opened = Some(Instant::now());
+        self.outcomes.clear();
+    }
+}
+
+pub(crate) struct CircuitBreaker {
+    config: CircuitBreakerConfig,
+    circuits: Mutex<HashMap<String, Circuit>>,
+}
+
+/// Permission to send one request. Dropping it unrecorded, e.g. when the
+/// request is cancelled, frees a half-open probe slot.
+pub(crate) struct Ticket<'a> {
+    breaker: &'a CircuitBreaker,
+    key: String,
+    probe: bool,
+}
+
+impl CircuitBreaker {
+    pub(crate) fn new(config: CircuitBreakerConfig) -> Self {
+        CircuitBreaker { config, circuits: Mutex::new(HashMap::new()) }
+    }
+
+    /// The circuit a request belongs to.
+    pub(crate) fn key(&self, base_url: &str, namespace: Option<&str>) -> String {
+        match (self.config.scope, namespace) {
+            (BreakerScope::Namespace, Some(ns)) => format!("{}/{}", base_url, ns),
+            _ => base_url.to_string(),
+        }
+    }
+
+    pub(crate) fn state(&self, key: &str) -> CircuitState {
+        let circuits = self.circuits.lock().unwrap();
+        match circuits.get(key) {
+            Some(c) if c.state == CircuitState::Open && self.reopened(c) => CircuitState::HalfOpen,
+            Some(c) => c.state,
+            None => CircuitState::Closed,
+        }
+    }
+
+    fn reopened(&self, circuit: &Circuit) -> bool {
+        circuit.opened.is_some_and(|t| t.elapsed() >= self.config.open_duration)
+    }
+
+    /// Let a request through, or fail fast if the circuit is open.
+    pub(crate) fn admit(&self, key: String) -> Result<Ticket<'_>> {
+        let mut circuits = self.circuits.lock().unwrap();
+        let Some(circuit) = circuits.get_mut(&key) else {
+            return Ok(Ticket { breaker: self, key, probe: false });
+        };
+        if circuit.state == CircuitState::Open && self.reopened(circuit) {
+            circuit.state = CircuitState::HalfOpen;
+            circuit.probes = 0;
+        }
+        let probe = match circuit.state {
+            CircuitState::Closed => false,
+            CircuitState::HalfOpen if circuit.probes < self.config.half_open_requests.max(1) => {
+                circuit.probes += 1;
+                true
+            }
+            _ => return Err(Error::CircuitOpen { scope: key }),
+        };
+        Ok(Ticket { breaker: self, key, probe })
+    }
+
+    /// Record how a request went. Connection failures, timeouts and other
+    /// errors sending the request, 5xx and 429 responses, and slow calls are
+    /// failures; other errors, such as a body that doesn't decode, mean the
+    /// endpoint is healthy.
+    ///
+    /// Closed circuits with no failures in their window are dropped whenever
+    /// a new circuit is added, so per-namespace circuits don't accumulate.
+    pub(crate) fn record<T>(&self, mut ticket: Ticket<'_>, result: &Result<T>, latency: Duration) {
+        let failed = latency > self.config.slow_call
+            || matches!(result, Err(Error::Http(e)) if e.is_connect() || e.is_timeout() || e.is_request())
+            || matches!(result, Err(Error::Api { status, .. }) if *status >= 500 || *status == 429);
+
+        let mut circuits = self.circuits.lock().unwrap();
+        let key = std::mem::take(&mut ticket.key);
+        if !circuits.contains_key(&key) {
+            circuits.retain(|_, c| c.state != CircuitState::Closed || c.outcomes.contains(&true));
+        }
+        let circuit = circuits.entry(key).or_default();
+        if std::mem::take(&mut ticket.probe) {
+            circuit.probes = circuit.probes.saturating_sub(1);
+            if failed {
+                circuit.open();
+            } else if circuit.state == CircuitState::HalfOpen {
+                circuit.state = CircuitState::Closed;
+                circuit.outcomes.clear();
+            }
+        } else if circuit.state == CircuitState::Closed {
+            if circuit.outcomes.len() == self.config.window_size.max(1) {
+                circuit.outcomes.pop_front();
+            }
+            circuit.outcomes.push_back(failed);
+            let failures = circuit.outcomes.iter().filter(|f| **f).count();
+            if circuit.outcomes.len() >= self.config.min_requests
+                && failures as f64 >= self.config.failure_rate * circuit.outcomes.len() as f64
+            {
+                circuit.open();
+            }
+        }
+    }
+}
+
+impl Drop for Ticket<'_> {
+    fn drop(&mut self) {
+        if self.probe
+            && let Some(circuit) = self.breaker.circuits.lock().unwrap().get_mut(&self.key)
+        {
+            circuit.probes = circuit.probes.saturating_sub(1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn breaker(open_duration: Duration) -> CircuitBreaker {
+        CircuitBreaker::new(CircuitBreakerConfig {
+            window_size: 4,
+            min_requests: 4,
+            failure_rate: 0.5,
+            open_duration,
+            ..Default::default()
+        })
+    }
+
+    fn ok() -> Result<()> {
+        Ok(())
+    }
+
+    fn unavailable() -> Result<()> {
+        Err(Error::Api { status: 503, message: String::new() })
+    }
+
+    fn send(breaker: &CircuitBreaker, result: Result<()>) {
+        let ticket = breaker.admit("url".to_string()).unwrap();
+        breaker.record(ticket, &result, Duration::ZERO);
+    }
+
+    #[test]
+    fn test_opens_on_failure_rate() {
+        let breaker = breaker(Duration::from_secs(60));
+        send(&breaker, ok());
+        send(&breaker, Err(Error::Api { status: 404, message: String::new() }));
+        send(&breaker, unavailable());
+        assert_eq!(breaker.state("url"), CircuitState::Closed);
+        send(&breaker, unavailable());
+        assert_eq!(breaker.state("url"), CircuitState::Open);
+        assert!(matches!(breaker.admit("url".to_string()), Err(Error::CircuitOpen { .. })));
+    }
+
+    #[test]
+    fn test_slow_calls_count_as_failures() {
+        let breaker = breaker(Duration::from_secs(60));
+        for _ in 0..4 {
+            let ticket = breaker.admit("url".to_string()).unwrap();
+            breaker.record(ticket, &ok(), Duration::from_secs(11));
+        }
+        assert_eq!(breaker.state("url"), CircuitState::Open);
+    }
+
+    #[test]
+    fn test_half_open_probes() {
+        let breaker = breaker(Duration::ZERO);
+        for _ in 0..4 {
+            send(&breaker, unavailable());
+        }
+        assert_eq!(breaker.state("url"), CircuitState::HalfOpen);
+
+        // One probe at a time; a cancelled probe frees its slot.
+        let probe = breaker.admit("url".to_string()).unwrap();
+        assert!(breaker.admit("url".to_string()).is_err());
+        drop(probe);
+
+        // A failed probe reopens; a successful one closes.
+        send(&breaker, unavailable());
+        send(&breaker, ok());
+        assert_eq!(breaker.state("url"), CircuitState::Closed);
+    }
+
+    #[tokio::test]
+    async fn test_only_transport_errors_count() {
+        use std::io::{Read, Write};
+
+        // A server that answers every request with a body that isn't JSON.
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        std::thread::spawn(move || {
+            for mut stream in listener.incoming().flatten() {
+                let _ = stream.read(&mut [0; 1024]);
+                let _ = stream.write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 3\r\nconnection: close\r\n\r\nnot");
+            }
+        });
+        let client = reqwest::Client::new();
+        let breaker = breaker(Duration::from_secs(60));
+        for _ in 0..4 {
+            let response = client.get(&url).send().await.unwrap();
+            let decoded = response.json::<serde_json::Value>().await.map_err(Error::from);
+            assert!(matches!(&decoded, Err(Error::Http(e)) if e.is_decode()));
+            send(&breaker, decoded.map(drop));
+        }
+        assert_eq!(breaker.state("url"), CircuitState::Closed);
+
+        // Nothing listens on a port just released. Two refusals in the window
+        // of four reach the failure rate.
+        let closed = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+        for _ in 0..2 {
+            let refused = client.get(format!("http://{}", closed)).send().await.map_err(Error::from);
+            assert!(matches!(&refused, Err(Error::Http(e)) if e.is_connect()));
+            send(&breaker, refused.map(drop));
+        }
+        assert_eq!(breaker.state("url"), CircuitState::Open);
+    }
+
+    #[test]
+    fn test_healthy_circuits_are_dropped() {
+        let breaker = breaker(Duration::from_secs(60));
+        let keys = || {
+            let mut keys: Vec<String> = breaker.circuits.lock().unwrap().keys().cloned().collect();
+            keys.sort();
+            keys
+        };
+        let send_to = |key: &str, result: Result<()>| {
+            let ticket = breaker.admit(key.to_string()).unwrap();
+            breaker.record(ticket, &result, Duration::ZERO);
+        };
+
+        send_to("a", ok());
+        send_to("b", unavailable());
+        send_to("b", ok());
+        assert_eq!(keys(), vec!["b"]);
+
+        // "b" still has a failure in its window.
+        send_to("c", ok());
+        assert_eq!(keys(), vec!["b", "c"]);
+        for _ in 0..4 {
+            send_to("b", ok());
+        }
+        send_to("d", ok());
+        assert_eq!(keys(), vec!["d"]);
+    }
+
+    #[test]
+    fn test_namespace_scope() {
+        let breaker = CircuitBreaker::new(CircuitBreakerConfig { scope: BreakerScope::Namespace, ..Default::default() });
+        assert_eq!(breaker.key("https://api", Some("docs")), "https://api/docs");
+        assert_eq!(breaker.key("https://api", None), "https://api");
+        let breaker = CircuitBreaker::new(CircuitBreakerConfig::default());
+        assert_eq!(breaker.key("https://api", Some("docs")), "https://api");
+    }
+}
@@ -0,0 +1,194 @@
+use lru::LruCache;
+use std::collections::HashMap;
+use std::num::NonZeroUsize;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::{ConsistencyLevel, QueryParams, QueryResponse};
+
+/// Settings for the client-side query result cache enabled with
+/// `Client::with_query_cache`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueryCacheConfig {
+    /// Most responses kept; the least recently used are evicted first.
+    pub capacity: usize,
+    /// How long a response is served from the cache.
+    pub ttl: Duration,
+}
+
+impl Default for QueryCacheConfig {
+    fn default() -> Self {
+        QueryCacheConfig { capacity: 1024, ttl: Duration::from_secs(60) }
+    }
+}
+
+/// A query's cache key: the namespace, its write generation when the query
+/// was sent, and the canonical JSON of the params.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct CacheKey {
+    namespace: Arc<str>,
+    generation: u64,
+    params: String,
+}
+
+struct CacheState {
+    entries: LruCache<CacheKey, (Instant, QueryResponse)>,
+    /// Bumped by every write to a namespace, so older entries are never hit
+    /// again and age out of the LRU. Keys share the namespace's name, so a
+    /// namespace no key refers to, cached or in flight, can be dropped and
+    /// restart at 0 without a stale response landing on a fresh key.
+    generations: HashMap<Arc<str>, u64>,
+}
+
+pub(crate) struct QueryCache {
+    ttl: Duration,
+    state: Mutex<CacheState>,
+}
+
+impl QueryCache {
+    pub(crate) fn new(config: QueryCacheConfig) -> Self {
+        let capacity = NonZeroUsize::new(config.capacity).unwrap_or(NonZeroUsize::MIN);
+        QueryCache {
+            ttl: config.ttl,
+            state: Mutex::new(CacheState { entries: LruCache::new(capacity), generations: HashMap::new() }),
+        }
+    }
+
+    /// The key for a query, or `None` if it must bypass the cache. Only
+    /// eventually consistent queries are cached; strong consistency, the
+    /// server default, promises to reflect every write.
+    pub(crate) fn key(&self, namespace: &str, params: &QueryParams) -> Option<CacheKey> {
+        let eventual = params.consistency.as_ref().is_some_and(|c| c.level == ConsistencyLevel::Eventual);
+        if !eventual {
+            return None;
+        }
+        let params = canonical_json(params)?;
+        let generations = &mut self.state.lock().unwrap().generations;
+        if let Some((name, generation)) = generations.get_key_value(namespace) {
+            return Some(CacheKey { namespace: name.clone(), generation: *generation, params });
+        }
+        generations.retain(|name, _| Arc::strong_count(name) > 1);
+        let name: Arc<str> = Arc::from(namespace);
+        generations.insert(name.clone(), 0);
+        Some(CacheKey { namespace: name, generation: 0, params })
+    }
+
+    pub(crate) fn get(&self, key: &CacheKey) -> Option<QueryResponse> {
+        let mut state = self.state.lock().unwrap();
+        match state.entries.get(key) {
+            Some((inserted, response)) if inserted.elapsed() < self.ttl => Some(response.clone()),
+            Some(_) => {
+                state.entries.pop(key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    pub(crate) fn insert(&self, key: CacheKey, response: QueryResponse) {
+        self.state.lock().unwrap().entries.put(key, (Instant::now(), response));
+    }
+
+    /// Drop every cached response for the namespace. Without a generation,
+    /// no key for it exists to go stale.
+    pub(crate) fn invalidate(&self, namespace: &str) {
+        if let Some(generation) = self.state.lock().unwrap().generations.get_mut(namespace) {
+            *generation += 1;
+        }
+    }
+}
+
+/// The params as JSON with object keys sorted, so equal params give equal
+/// strings regardless of `HashMap` iteration order.
+pub(crate) fn canonical_json(params: &impl serde::Serialize) -> Option<String> {
+    serde_json::to_value(params).ok().map(|v| v.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Consistency, Filter};
+
+    fn eventual(filter: Filter) -> QueryParams {
+        QueryParams {
+            filters: Some(filter),
+            consistency: Some(Consistency { level: ConsistencyLevel::Eventual }),
+            ..Default::default()
+        }
+    }
+
+    fn response(n: u64) -> QueryResponse {
+        serde_json::from_value(serde_json::json!({ "rows": [{"id": n}] })).unwrap()
+    }
+
+    #[test]
+    fn test_hit_and_invalidate() {
+        let cache = QueryCache::new(QueryCacheConfig::default());
+        let params = eventual(Filter::eq("a", 1));
+        let key = cache.key("ns", &params).unwrap();
+        assert!(cache.get(&key).is_none());
+
+        cache.insert(key, response(1));
+        let key = cache.key("ns", &params).unwrap();
+        assert_eq!(cache.get(&key).unwrap().rows, response(1).rows);
+        assert!(cache.get(&cache.key("other", &params).unwrap()).is_none());
+        assert!(cache.get(&cache.key("ns", &eventual(Filter::eq("a", 2))).unwrap()).is_none());
+
+        cache.invalidate("ns");
+        assert!(cache.get(&cache.key("ns", &params).unwrap()).is_none());
+        // A response to a query sent before the write lands under the old key.
+        cache.insert(key, response(1));
+        assert!(cache.get(&cache.key("ns", &params).unwrap()).is_none());
+    }
+
+    #[test]
+    fn test_unused_generations_are_dropped() {
+        let cache = QueryCache::new(QueryCacheConfig { capacity: 1, ..Default::default() });
+        let namespaces = || {
+            let mut names: Vec<String> = cache.state.lock().unwrap().generations.keys().map(|n| n.to_string()).collect();
+            names.sort();
+            names
+        };
+        let params = eventual(Filter::eq("a", 1));
+
+        cache.insert(cache.key("a", &params).unwrap(), response(1));
+        let in_flight = cache.key("b", &params).unwrap();
+        cache.invalidate("b");
+        // "a"'s entry is evicted, but a key for "b" is still in flight.
+        cache.insert(cache.key("c", &params).unwrap(), response(3));
+        cache.key("d", &params);
+        assert_eq!(namespaces(), vec!["b", "c", "d"]);
+
+        // The stale response lands under the old generation and is never hit.
+        cache.insert(in_flight, response(2));
+        cache.key("e", &params);
+        assert_eq!(namespaces(), vec!["b", "e"]);
+        assert!(cache.get(&cache.key("b", &params).unwrap()).is_none());
+    }
+
+    #[test]
+    fn test_strong_consistency_bypasses() {
+        let cache = QueryCache::new(QueryCacheConfig::default());
+        let mut params = eventual(Filter::eq("a", 1));
+        params.consistency = Some(Consistency { level: ConsistencyLevel::Strong });
+        assert!(cache.key("ns", &params).is_none());
+        params.consistency = None;
+        assert!(cache.key("ns", &params).is_none());
+    }
+
+    #[test]
+    fn test_ttl_and_capacity() {
+        let cache = QueryCache::new(QueryCacheConfig { capacity: 2, ttl: Duration::ZERO });
+        let key = cache.key("ns", &eventual(Filter::eq("a", 1))).unwrap();
+        cache.insert(key.clone(), response(1));
+        assert!(cache.get(&key).is_none());
+
+        let cache = QueryCache::new(QueryCacheConfig { capacity: 2, ..Default::default() });
+        let keys: Vec<_> = (0..3).map(|i| cache.key("ns", &eventual(Filter::eq("a", i))).unwrap()).collect();
+        for (i, key) in keys.iter().enumerate() {
+            cache.insert(key.clone(), response(i as u64));
+        }
+        assert!(cache.get(&keys[0]).is_none());
+        assert!(cache.get(&keys[2]).is_some());
+    }
+}
@@ -1,3 +1,12 @@
+use crate::breaker::{CircuitBreaker, CircuitBreakerConfig, CircuitState};
+#[cfg(any(feature = "gzip", feature = "zstd"))]
+use crate::compression::RequestCompression;
+use crate::cache::{QueryCache, QueryCacheConfig};
+use crate::hedge::{self, Hedger, HedgingPolicy};
+use crate::limits::{ConcurrencyLimiter, ConcurrencyLimits, Permits, classify};
+use crate::rate_limit::{RateLimiter, RateLimits};
+use crate::single_flight::SingleFlightGroups;
+use crate::streaming::BodyError;
 use crate::{Error, Namespace, NamespacesResponse, Result};
 
 const DEFAULT_BASE_URL: &str = "https://api.turbopuffer.com";
@@ -14,36 +23,55 @@ pub struct NamespacesParams {
     pub page_size: Option<u32>,
 }
 
+/// A serialized request body and its `Content-Encoding`, if compressed.
+pub(crate) struct RequestBody {
+    pub(crate) body: reqwest::Body,
+    pub(crate) content_encoding: Option<&'static str>,
+    /// Where a streamed body keeps the error that ended it.
+    pub(crate) error: Option<BodyError>,
+}
+
 pub struct Client {
     pub(crate) api_key: String,
     pub(crate) base_url: String,
     pub(crate) http: reqwest::Client,
+    pub(crate) query_cache: Option<QueryCache>,
+    pub(crate) single_flight: Option<SingleFlightGroups>,
+    pub(crate) hedger: Option<Hedger>,
+    pub(crate) limiter: Option<ConcurrencyLimiter>,
+    pub(crate) rate_limiter: Option<RateLimiter>,
+    pub(crate) breaker: Option<CircuitBreaker>,
+    #[cfg(any(feature = "gzip", feature = "zstd"))]
+    pub(crate) compression: Option<RequestCompression>,
 }
 
 impl Client {
-    pub fn new(api_key: impl Into<String>) -> Self {
+    fn from_parts(api_key: String, base_url: String) -> Self {
         Self {
-            api_key: api_key.into(),
-            base_url: DEFAULT_BASE_URL.to_string(),
+            api_key,
+            base_url,
             http: reqwest::Client::new(),
+            query_cache: None,
+            single_flight: None,
+            hedger: None,
+            limiter: None,
+            rate_limiter: None,
+            breaker: None,
+            #[cfg(any(feature = "gzip", feature = "zstd"))]
+            compression: None,
         }
     }
 
+    pub fn new(api_key: impl Into<String>) -> Self {
+        Self::from_parts(api_key.into(), DEFAULT_BASE_URL.to_string())
+    }
+
     pub fn with_region(api_key: impl Into<String>, region: &str) -> Self {
-        let base_url = format!("https://{}.turbopuffer.com", region);
-        Self {
-            api_key: api_key.into(),
-            base_url,
-            http: reqwest::Client::new(),
-        }
+        Self::from_parts(api_key.into(), format!("https://{}.turbopuffer.com", region))
     }
 
     pub fn with_base_url(api_key: impl Into<String>, base_url: impl Into<String>) -> Self {
-        Self {
-            api_key: api_key.into(),
-            base_url: base_url.into(),
-            http: reqwest::Client::new(),
-        }
+        Self::from_parts(api_key.into(), base_url.into())
     }
 
     pub fn from_env() -> Result<Self> {
@@ -57,11 +85,72 @@ impl Client {
             .map(|r| format!("https://{}.turbopuffer.com", r))
             .unwrap_or_else(|_| DEFAULT_BASE_URL.to_string());
 
-        Ok(Self {
-            api_key,
-            base_url,
-            http: reqwest::Client::new(),
-        })
+        Ok(Self::from_parts(api_key, base_url))
+    }
+
+    /// Cache eventually consistent query results in memory. Writes and
+    /// `delete_all` through this client invalidate the namespace's entries;
+    /// writes from elsewhere show up once entries expire.
+    pub fn with_query_cache(mut self, config: QueryCacheConfig) -> Self {
+        self.query_cache = Some(QueryCache::new(config));
+        self
+    }
+
+    /// Coalesce concurrent identical `query` and `metadata` calls into one
+    /// request whose response goes to every caller. A strongly consistent
+    /// query that joins one already in flight may miss a write acknowledged
+    /// in between.
+    pub fn with_single_flight(mut self) -> Self {
+        self.single_flight = Some(SingleFlightGroups::new());
+        self
+    }
+
+    /// Send a duplicate `query` or `multi_query` request when the first is
+    /// slower than recent latencies suggest, and take whichever succeeds first.
+    pub fn with_hedging(mut self, policy: HedgingPolicy) -> Self {
+        self.hedger = Some(Hedger::new(policy));
+        self
+    }
+
+    /// Limit in-flight requests globally, per namespace and per operation.
+    pub fn with_concurrency_limits(mut self, limits: ConcurrencyLimits) -> Self {
+        self.limiter = Some(ConcurrencyLimiter::new(limits));
+        self
+    }
+
+    /// Limit requests and billed bytes per second, globally and per
+    /// namespace. Fails with `Error::InvalidArgument` if a rate isn't
+    /// positive.
+    pub fn with_rate_limits(mut self, limits: RateLimits) -> Result<Self> {
+        self.rate_limiter = Some(RateLimiter::new(limits)?);
+        Ok(self)
+    }
+
+    /// Compress request bodies over a size threshold. Responses are
+    /// decompressed transparently whenever the `gzip` or `zstd` feature is on.
+    #[cfg(any(feature = "gzip", feature = "zstd"))]
+    pub fn with_request_compression(mut self, config: RequestCompression) -> Self {
+        self.compression = Some(config);
+        self
+    }
+
+    /// Fail fast with `Error::CircuitOpen` while the endpoint, or a
+    /// namespace, is failing or slow.
+    pub fn with_circuit_breaker(mut self, config: CircuitBreakerConfig) -> Self {
+        self.breaker = Some(CircuitBreaker::new(config));
+        self
+    }
+
+    /// The state of the circuit breaker for requests outside a namespace,
+    /// which is every request unless it is scoped per namespace. `None` if
+    /// no breaker is configured.
+    pub fn circuit_state(&self) -> Option<CircuitState> {
+        self.circuit_state_for(None)
+    }
+
+    pub(crate) fn circuit_state_for(&self, namespace: Option<&str>) -> Option<CircuitState> {
+        let breaker = self.breaker.as_ref()?;
+        Some(breaker.state(&breaker.key(&self.base_url, namespace)))
     }
 
     pub fn namespace(&self, name: impl Into<String>) -> Namespace<'_> {
@@ -94,6 +183,82 @@ impl Client {
         T: serde::Serialize + ?Sized,
         R: serde::de::DeserializeOwned,
     {
+        let body = match body {
+            Some(body) => Some(self.encode(body)?),
+            None => None,
+        };
+        self.execute(method, path, body).await
+    }
+
+    /// The JSON body, compressed if it is large enough and the client
+    /// compresses requests.
+    pub(crate) fn encode<T: serde::Serialize + ?Sized>(&self, body: &T) -> Result<RequestBody> {
+        let body = serde_json::to_vec(body)?;
+        #[cfg(any(feature = "gzip", feature = "zstd"))]
+        if let Some((compressed, encoding)) = self.compression.as_ref().and_then(|c| c.compress(&body)) {
+            return Ok(RequestBody { body: compressed.into(), content_encoding: Some(encoding), error: None });
+        }
+        Ok(RequestBody { body: body.into(), content_encoding: None, error: None })
+    }
+
+    /// Send a request with a prepared body, applying the circuit breaker and
+    /// rate and concurrency limits.
+    pub(crate) async fn execute<R>(&self, method: reqwest::Method, path: &str, body: Option<RequestBody>) -> Result<R>
+    where
+        R: serde::de::DeserializeOwned,
+    {
+        self.execute_with(method, path, body, |resp, permits| async move {
+            let result = resp.json().await;
+            drop(permits);
+            Ok(result?)
+        })
+        .await
+    }
+
+    /// `execute`, handing the successful response to `read` along with the
+    /// concurrency permits, which it may hold on to while the body is read.
+    pub(crate) async fn execute_with<T, F, Fut>(
+        &self,
+        method: reqwest::Method,
+        path: &str,
+        body: Option<RequestBody>,
+        read: F,
+    ) -> Result<T>
+    where
+        F: FnOnce(reqwest::Response, Option<Permits>) -> Fut,
+        Fut: std::future::Future<Output = Result<T>>,
+    {
+        let (namespace, operation) = classify(&method, path);
+        let ticket = match &self.breaker {
+            Some(breaker) => Some(breaker.admit(breaker.key(&self.base_url, namespace))?),
+            None => None,
+        };
+        if let Some(rate_limiter) = &self.rate_limiter {
+            rate_limiter.acquire(namespace).await;
+        }
+        let permits = match &self.limiter {
+            Some(limiter) => Some(limiter.acquire(namespace, operation).await),
+            None => None,
+        };
+
+        if let Some(hedger) = &self.hedger
+            && hedge::is_hedge()
+        {
+            hedger.sent_hedge();
+        }
+
+        let started = std::time::Instant::now();
+        let result = match self.send(method, path, body).await {
+            Ok(resp) => read(resp, permits).await,
+            Err(e) => Err(e),
+        };
+        if let (Some(breaker), Some(ticket)) = (&self.breaker, ticket) {
+            breaker.record(ticket, &result, started.elapsed());
+        }
+        result
+    }
+
+    async fn send(&self, method: reqwest::Method, path: &str, body: Option<RequestBody>) -> Result<reqwest::Response> {
         let url = format!("{}{}", self.base_url, path);
 
         let mut req = self.http
@@ -101,11 +266,21 @@ impl Client {
             .header("Authorization", format!("Bearer {}", self.api_key))
             .header("Content-Type", "application/json");
 
-        if let Some(body) = body {
-            req = req.json(body);
+        let mut body_error = None;
+        if let Some(RequestBody { body, content_encoding, error }) = body {
+            if let Some(encoding) = content_encoding {
+                req = req.header("Content-Encoding", encoding);
+            }
+            req = req.body(body);
+            body_error = error;
         }
 
-        let resp = req.send().await?;
+        // A body that failed to serialize is the caller's error, not the
+        // server's, so it isn't returned as `Error::Http`.
+        let resp = req.send().await.map_err(|e| match body_error.and_then(|b| b.take()) {
+            Some(e) => Error::Json(e),
+            None => Error::Http(e),
+        })?;
         let status = resp.status();
 
         if !status.is_success() {
@@ -116,8 +291,26 @@ impl Client {
             });
         }
 
-        let result = resp.json().await?;
-        Ok(result)
+        Ok(resp)
+    }
+
+    /// Count billed bytes against the rate limits, if any.
+    pub(crate) fn charge(&self, namespace: &str, bytes: u64) {
+        if let Some(rate_limiter) = &self.rate_limiter {
+            rate_limiter.charge(Some(namespace), bytes);
+        }
+    }
+
+    /// `request`, hedged if the client has a hedging policy.
+    pub(crate) async fn hedged<T, F, Fut>(&self, request: F) -> Result<T>
+    where
+        F: Fn() -> Fut,
+        Fut: std::future::Future<Output = Result<T>>,
+    {
+        match &self.hedger {
+            Some(hedger) => hedger.run(request).await,
+            None => request().await,
+        }
     }
 
     pub(crate) async fn request_no_body<R>(&self, method: reqwest::Method, path: &str) -> Result<R>
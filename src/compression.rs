@@ -0,0 +1,151 @@
+use std::io::Write;
+
+/// A `Content-Encoding` for request bodies.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    #[cfg(feature = "gzip")]
+    Gzip,
+    #[cfg(feature = "zstd")]
+    Zstd,
+}
+
+/// Settings for request body compression, enabled with
+/// `Client::with_request_compression`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RequestCompression {
+    pub algorithm: Compression,
+    /// Smaller bodies are sent uncompressed.
+    pub min_size: usize,
+}
+
+impl Default for RequestCompression {
+    /// zstd if enabled, otherwise gzip, for bodies of 1 KiB and up.
+    fn default() -> Self {
+        #[cfg(feature = "zstd")]
+        let algorithm = Compression::Zstd;
+        #[cfg(not(feature = "zstd"))]
+        let algorithm = Compression::Gzip;
+        RequestCompression { algorithm, min_size: 1024 }
+    }
+}
+
+impl Compression {
+    pub(crate) fn content_encoding(self) -> &'static str {
+        match self {
+            #[cfg(feature = "gzip")]
+            Compression::Gzip => "gzip",
+            #[cfg(feature = "zstd")]
+            Compression::Zstd => "zstd",
+        }
+    }
+
+    pub(crate) fn encoder(self, out: Vec<u8>) -> std::io::Result<Encoder> {
+        Ok(match self {
+            #[cfg(feature = "gzip")]
+            Compression::Gzip => Encoder::Gzip(flate2::write::GzEncoder::new(out, flate2::Compression::fast())),
+            #[cfg(feature = "zstd")]
+            Compression::Zstd => Encoder::Zstd(zstd::Encoder::new(out, 0)?),
+        })
+    }
+}
+
+/// A compressing writer into a buffer.
+pub(crate) enum Encoder {
+    #[cfg(feature = "gzip")]
+    Gzip(flate2::write::GzEncoder<Vec<u8>>),
+    #[cfg(feature = "zstd")]
+    Zstd(zstd::Encoder<'static, Vec<u8>>),
+}
+
+impl Encoder {
+    /// The compressed bytes produced so far.
+    pub(crate) fn take_output(&mut self) -> Vec<u8> {
+        match self {
+            #[cfg(feature = "gzip")]
+            Encoder::Gzip(e) => std::mem::take(e.get_mut()),
+            #[cfg(feature = "zstd")]
+            Encoder::Zstd(e) => std::mem::take(e.get_mut()),
+        }
+    }
+
+    pub(crate) fn finish(self) -> std::io::Result<Vec<u8>> {
+        match self {
+            #[cfg(feature = "gzip")]
+            Encoder::Gzip(e) => e.finish(),
+            #[cfg(feature = "zstd")]
+            Encoder::Zstd(e) => e.finish(),
+        }
+    }
+}
+
+impl Write for Encoder {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match self {
+            #[cfg(feature = "gzip")]
+            Encoder::Gzip(e) => e.write(buf),
+            #[cfg(feature = "zstd")]
+            Encoder::Zstd(e) => e.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self {
+            #[cfg(feature = "gzip")]
+            Encoder::Gzip(e) => e.flush(),
+            #[cfg(feature = "zstd")]
+            Encoder::Zstd(e) => e.flush(),
+        }
+    }
+}
+
+impl RequestCompression {
+    /// The body compressed and its `Content-Encoding`, or `None` if it is
+    /// below the threshold or compressing fails, so it is sent as is.
+    pub(crate) fn compress(&self, body: &[u8]) -> Option<(Vec<u8>, &'static str)> {
+        if body.len() < self.min_size {
+            return None;
+        }
+        let mut encoder = self.algorithm.encoder(Vec::new()).ok()?;
+        encoder.write_all(body).ok()?;
+        Some((encoder.finish().ok()?, self.algorithm.content_encoding()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    fn body() -> Vec<u8> {
+        serde_json::to_vec(&vec![0.125f32; 512]).unwrap()
+    }
+
+    #[test]
+    fn test_threshold() {
+        let config = RequestCompression { min_size: 1 << 20, ..Default::default() };
+        assert!(config.compress(&body()).is_none());
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn test_gzip_round_trip() {
+        let config = RequestCompression { algorithm: Compression::Gzip, min_size: 0 };
+        let (compressed, encoding) = config.compress(&body()).unwrap();
+        assert_eq!(encoding, "gzip");
+        assert!(compressed.len() < body().len() / 10);
+        let mut out = Vec::new();
+        flate2::read::GzDecoder::new(&compressed[..]).read_to_end(&mut out).unwrap();
+        assert_eq!(out, body());
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn test_zstd_round_trip() {
+        let config = RequestCompression { algorithm: Compression::Zstd, min_size: 0 };
+        let (compressed, encoding) = config.compress(&body()).unwrap();
+        assert_eq!(encoding, "zstd");
+        let mut out = Vec::new();
+        zstd::Decoder::new(&compressed[..]).unwrap().read_to_end(&mut out).unwrap();
+        assert_eq!(out, body());
+    }
+}
@@ -1,5 +1,8 @@
+use std::sync::Arc;
 use thiserror::Error;
 
+use crate::LintIssue;
+
 #[derive(Error, Debug)]
 pub enum Error {
     #[error("HTTP error: {0}")]
@@ -10,6 +13,53 @@ pub enum Error {
 
     #[error("JSON error: {0}")]
     Json(#[from] serde_json::Error),
+
+    #[error("Invalid timestamp: {0}")]
+    InvalidTimestamp(String),
+
+    #[error("Invalid document ID: {0}")]
+    InvalidId(String),
+
+    #[error("Invalid vector: {0}")]
+    InvalidVector(String),
+
+    /// An argument the client rejected before sending anything.
+    #[error("Invalid argument: {0}")]
+    InvalidArgument(String),
+
+    #[error("Filter parse error at column {column}: {message}")]
+    FilterParse { column: usize, message: String },
+
+    #[error("Query failed lint: {}", .0.iter().map(ToString::to_string).collect::<Vec<_>>().join("; "))]
+    Lint(Vec<LintIssue>),
+
+    /// The circuit breaker for `scope` is open, so the request wasn't sent.
+    #[error("Circuit breaker open for {scope}")]
+    CircuitOpen { scope: String },
+
+    /// An error from a request whose result was shared by several callers,
+    /// e.g. coalesced by single-flight.
+    #[error("{0}")]
+    Shared(Arc<Error>),
+}
+
+impl Error {
+    /// A copy of a shared error. Variants that can be cloned are; HTTP and
+    /// JSON errors are wrapped in `Error::Shared`.
+    pub(crate) fn shared(e: &Arc<Error>) -> Error {
+        match &**e {
+            Error::Api { status, message } => Error::Api { status: *status, message: message.clone() },
+            Error::InvalidTimestamp(s) => Error::InvalidTimestamp(s.clone()),
+            Error::InvalidId(s) => Error::InvalidId(s.clone()),
+            Error::InvalidVector(s) => Error::InvalidVector(s.clone()),
+            Error::InvalidArgument(s) => Error::InvalidArgument(s.clone()),
+            Error::FilterParse { column, message } => Error::FilterParse { column: *column, message: message.clone() },
+            Error::Lint(issues) => Error::Lint(issues.clone()),
+            Error::CircuitOpen { scope } => Error::CircuitOpen { scope: scope.clone() },
+            Error::Shared(inner) => Error::Shared(inner.clone()),
+            Error::Http(_) | Error::Json(_) => Error::Shared(e.clone()),
+        }
+    }
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
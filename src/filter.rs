@@ -1,5 +1,13 @@
+use serde::de::{self, Deserializer};
 use serde::ser::{SerializeSeq, Serializer};
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+mod dsl;
+mod eval;
+mod simplify;
+
+pub use eval::CompiledFilter;
 
 #[derive(Debug, Clone, PartialEq, Deserialize)]
 pub struct ContainsAllTokensParams {
@@ -85,18 +93,50 @@ impl Filter {
         Filter::Contains { attr: attr.into(), value: value.into() }
     }
 
+    pub fn not_contains(attr: impl Into<String>, value: impl Into<serde_json::Value>) -> Self {
+        Filter::NotContains { attr: attr.into(), value: value.into() }
+    }
+
     pub fn contains_any(attr: impl Into<String>, values: Vec<serde_json::Value>) -> Self {
         Filter::ContainsAny { attr: attr.into(), values }
     }
 
+    pub fn not_contains_any(attr: impl Into<String>, values: Vec<serde_json::Value>) -> Self {
+        Filter::NotContainsAny { attr: attr.into(), values }
+    }
+
+    pub fn any_lt(attr: impl Into<String>, value: impl Into<serde_json::Value>) -> Self {
+        Filter::AnyLt { attr: attr.into(), value: value.into() }
+    }
+
+    pub fn any_lte(attr: impl Into<String>, value: impl Into<serde_json::Value>) -> Self {
+        Filter::AnyLte { attr: attr.into(), value: value.into() }
+    }
+
+    pub fn any_gt(attr: impl Into<String>, value: impl Into<serde_json::Value>) -> Self {
+        Filter::AnyGt { attr: attr.into(), value: value.into() }
+    }
+
+    pub fn any_gte(attr: impl Into<String>, value: impl Into<serde_json::Value>) -> Self {
+        Filter::AnyGte { attr: attr.into(), value: value.into() }
+    }
+
     pub fn glob(attr: impl Into<String>, pattern: impl Into<String>) -> Self {
         Filter::Glob { attr: attr.into(), pattern: pattern.into() }
     }
 
+    pub fn not_glob(attr: impl Into<String>, pattern: impl Into<String>) -> Self {
+        Filter::NotGlob { attr: attr.into(), pattern: pattern.into() }
+    }
+
     pub fn iglob(attr: impl Into<String>, pattern: impl Into<String>) -> Self {
         Filter::IGlob { attr: attr.into(), pattern: pattern.into() }
     }
 
+    pub fn not_iglob(attr: impl Into<String>, pattern: impl Into<String>) -> Self {
+        Filter::NotIGlob { attr: attr.into(), pattern: pattern.into() }
+    }
+
     pub fn regex(attr: impl Into<String>, pattern: impl Into<String>) -> Self {
         Filter::Regex { attr: attr.into(), pattern: pattern.into() }
     }
@@ -109,6 +149,8 @@ impl Filter {
         Filter::Or(filters)
     }
 
+    // Kept alongside `std::ops::Not` so `Filter::not(f)` works without importing the trait.
+    #[allow(clippy::should_implement_trait)]
     pub fn not(filter: Filter) -> Self {
         Filter::Not(Box::new(filter))
     }
@@ -132,6 +174,52 @@ impl Filter {
             params: Some(params),
         }
     }
+
+    pub fn contains_token_sequence(attr: impl Into<String>, value: impl Into<String>) -> Self {
+        Filter::ContainsTokenSequence { attr: attr.into(), value: value.into() }
+    }
+}
+
+impl std::ops::BitAnd for Filter {
+    type Output = Filter;
+
+    /// Combine with `And`, flattening nested `And`s on either side.
+    fn bitand(self, rhs: Filter) -> Filter {
+        let mut filters = match self {
+            Filter::And(filters) => filters,
+            other => vec![other],
+        };
+        match rhs {
+            Filter::And(rhs) => filters.extend(rhs),
+            other => filters.push(other),
+        }
+        Filter::And(filters)
+    }
+}
+
+impl std::ops::BitOr for Filter {
+    type Output = Filter;
+
+    /// Combine with `Or`, flattening nested `Or`s on either side.
+    fn bitor(self, rhs: Filter) -> Filter {
+        let mut filters = match self {
+            Filter::Or(filters) => filters,
+            other => vec![other],
+        };
+        match rhs {
+            Filter::Or(rhs) => filters.extend(rhs),
+            other => filters.push(other),
+        }
+        Filter::Or(filters)
+    }
+}
+
+impl std::ops::Not for Filter {
+    type Output = Filter;
+
+    fn not(self) -> Filter {
+        Filter::Not(Box::new(self))
+    }
 }
 
 impl Serialize for Filter {
@@ -348,6 +436,99 @@ impl Serialize for ContainsAllTokensParams {
     }
 }
 
+impl<'de> Deserialize<'de> for Filter {
+    /// Accepts the tuple wire format produced by `Serialize`.
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let value = Value::deserialize(deserializer)?;
+        filter_from_value(value).map_err(de::Error::custom)
+    }
+}
+
+fn filter_from_value(value: Value) -> Result<Filter, String> {
+    let Value::Array(mut items) = value else {
+        return Err(format!("expected filter array, got {}", value));
+    };
+
+    // Logical ops: ["And", [filters]], ["Or", [filters]], ["Not", filter]
+    if items.len() == 2 && items[1].is_array() {
+        let op = items[0].as_str().unwrap_or_default().to_string();
+        let arg = items.pop().unwrap();
+        return match op.as_str() {
+            "And" | "Or" => {
+                let Value::Array(children) = arg else { unreachable!() };
+                let filters = children
+                    .into_iter()
+                    .map(filter_from_value)
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok(if op == "And" { Filter::And(filters) } else { Filter::Or(filters) })
+            }
+            "Not" => Ok(Filter::Not(Box::new(filter_from_value(arg)?))),
+            other => Err(format!("unknown logical operator {:?}", other)),
+        };
+    }
+
+    // Attribute ops: ["attr", "Op", value] or ["attr", "Op", value, params]
+    if items.len() != 3 && items.len() != 4 {
+        return Err(format!("expected filter of 3 or 4 elements, got {}", items.len()));
+    }
+    let params = if items.len() == 4 { items.pop() } else { None };
+    let value = items.pop().unwrap();
+    let (attr, op) = match (&items[0], &items[1]) {
+        (Value::String(attr), Value::String(op)) => (attr.clone(), op.clone()),
+        _ => return Err("expected attribute name and operator strings".to_string()),
+    };
+    if params.is_some() && op != "ContainsAllTokens" {
+        return Err(format!("unexpected parameters for {}", op));
+    }
+
+    let values = |value: Value| match value {
+        Value::Array(values) => Ok(values),
+        other => Err(format!("expected array for {}, got {}", op, other)),
+    };
+    let string = |value: Value| match value {
+        Value::String(s) => Ok(s),
+        other => Err(format!("expected string for {}, got {}", op, other)),
+    };
+
+    let filter = match op.as_str() {
+        "Eq" => Filter::Eq { attr, value },
+        "NotEq" => Filter::NotEq { attr, value },
+        "Lt" => Filter::Lt { attr, value },
+        "Lte" => Filter::Lte { attr, value },
+        "Gt" => Filter::Gt { attr, value },
+        "Gte" => Filter::Gte { attr, value },
+        "AnyLt" => Filter::AnyLt { attr, value },
+        "AnyLte" => Filter::AnyLte { attr, value },
+        "AnyGt" => Filter::AnyGt { attr, value },
+        "AnyGte" => Filter::AnyGte { attr, value },
+        "In" => Filter::In { attr, values: values(value)? },
+        "NotIn" => Filter::NotIn { attr, values: values(value)? },
+        "Contains" => Filter::Contains { attr, value },
+        "NotContains" => Filter::NotContains { attr, value },
+        "ContainsAny" => Filter::ContainsAny { attr, values: values(value)? },
+        "NotContainsAny" => Filter::NotContainsAny { attr, values: values(value)? },
+        "Glob" => Filter::Glob { attr, pattern: string(value)? },
+        "NotGlob" => Filter::NotGlob { attr, pattern: string(value)? },
+        "IGlob" => Filter::IGlob { attr, pattern: string(value)? },
+        "NotIGlob" => Filter::NotIGlob { attr, pattern: string(value)? },
+        "Regex" => Filter::Regex { attr, pattern: string(value)? },
+        "ContainsAllTokens" => Filter::ContainsAllTokens {
+            attr,
+            value: string(value)?,
+            params: params
+                .map(serde_json::from_value)
+                .transpose()
+                .map_err(|e| format!("invalid ContainsAllTokens params: {}", e))?,
+        },
+        "ContainsTokenSequence" => Filter::ContainsTokenSequence { attr, value: string(value)? },
+        other => return Err(format!("unknown filter operator {:?}", other)),
+    };
+    Ok(filter)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -489,6 +670,113 @@ mod tests {
         assert_eq!(json, r#"["price","Eq",19.99]"#);
     }
 
+    #[test]
+    fn test_operator_overloading() {
+        let f = Filter::eq("a", 1) & !Filter::glob("b", "x*");
+        assert_eq!(
+            serde_json::to_string(&f).unwrap(),
+            r#"["And",[["a","Eq",1],["Not",["b","Glob","x*"]]]]"#
+        );
+    }
+
+    #[test]
+    fn test_operator_flattening() {
+        let f = (Filter::eq("a", 1) & Filter::eq("b", 2)) & (Filter::eq("c", 3) & Filter::eq("d", 4));
+        assert_eq!(
+            f,
+            Filter::and(vec![
+                Filter::eq("a", 1),
+                Filter::eq("b", 2),
+                Filter::eq("c", 3),
+                Filter::eq("d", 4),
+            ])
+        );
+
+        let f = Filter::eq("a", 1) | Filter::eq("b", 2) | (Filter::eq("c", 3) & Filter::eq("d", 4));
+        assert_eq!(
+            f,
+            Filter::or(vec![
+                Filter::eq("a", 1),
+                Filter::eq("b", 2),
+                Filter::and(vec![Filter::eq("c", 3), Filter::eq("d", 4)]),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_negated_constructors_serialization() {
+        assert_eq!(
+            serde_json::to_string(&Filter::not_contains("tags", "go")).unwrap(),
+            r#"["tags","NotContains","go"]"#
+        );
+        assert_eq!(
+            serde_json::to_string(&Filter::not_contains_any("tags", vec!["go".into()])).unwrap(),
+            r#"["tags","NotContainsAny",["go"]]"#
+        );
+        assert_eq!(
+            serde_json::to_string(&Filter::not_glob("name", "a*")).unwrap(),
+            r#"["name","NotGlob","a*"]"#
+        );
+        assert_eq!(
+            serde_json::to_string(&Filter::not_iglob("name", "A*")).unwrap(),
+            r#"["name","NotIGlob","A*"]"#
+        );
+    }
+
+    #[test]
+    fn test_any_comparison_serialization() {
+        assert_eq!(serde_json::to_string(&Filter::any_lt("s", 1)).unwrap(), r#"["s","AnyLt",1]"#);
+        assert_eq!(serde_json::to_string(&Filter::any_lte("s", 1)).unwrap(), r#"["s","AnyLte",1]"#);
+        assert_eq!(serde_json::to_string(&Filter::any_gt("s", 1)).unwrap(), r#"["s","AnyGt",1]"#);
+        assert_eq!(serde_json::to_string(&Filter::any_gte("s", 1)).unwrap(), r#"["s","AnyGte",1]"#);
+    }
+
+    #[test]
+    fn test_contains_token_sequence_serialization() {
+        let f = Filter::contains_token_sequence("text", "quick fox");
+        let json = serde_json::to_string(&f).unwrap();
+        assert_eq!(json, r#"["text","ContainsTokenSequence","quick fox"]"#);
+    }
+
+    #[test]
+    fn test_deserialization() {
+        let f: Filter = serde_json::from_str(
+            r#"["And",[["name","Eq","foo"],["Not",["tags","ContainsAny",["a"]]],["Or",[]]]]"#,
+        )
+        .unwrap();
+        assert_eq!(
+            f,
+            Filter::and(vec![
+                Filter::eq("name", "foo"),
+                Filter::not(Filter::contains_any("tags", vec!["a".into()])),
+                Filter::or(vec![]),
+            ])
+        );
+
+        let f: Filter =
+            serde_json::from_str(r#"["text","ContainsAllTokens","quick",{"last_as_prefix":true}]"#).unwrap();
+        assert_eq!(
+            f,
+            Filter::contains_all_tokens_with_params(
+                "text",
+                "quick",
+                ContainsAllTokensParams { last_as_prefix: Some(true) },
+            )
+        );
+
+        // An attribute may share a name with a logical operator.
+        let f: Filter = serde_json::from_str(r#"["And","Eq",1]"#).unwrap();
+        assert_eq!(f, Filter::eq("And", 1));
+    }
+
+    #[test]
+    fn test_deserialization_errors() {
+        assert!(serde_json::from_str::<Filter>(r#"["a","Bogus",1]"#).is_err());
+        assert!(serde_json::from_str::<Filter>(r#"["a","In",1]"#).is_err());
+        assert!(serde_json::from_str::<Filter>(r#"["a","Eq",1,{}]"#).is_err());
+        assert!(serde_json::from_str::<Filter>(r#"{"a":1}"#).is_err());
+    }
+
     #[test]
     fn test_null_value() {
         let f = Filter::Eq { attr: "field".into(), value: serde_json::Value::Null };
@@ -0,0 +1,784 @@
+//! A textual syntax for filters, e.g.
+//! `age >= 18 AND (status IN ('active', 'pending') OR NOT deleted = true)`.
+//!
+//! Operators map one-to-one onto `Filter` variants:
+//!
+//! | Syntax                                   | Variant                 |
+//! |------------------------------------------|-------------------------|
+//! | `a = v`, `a != v`                        | `Eq`, `NotEq`           |
+//! | `a < v`, `a <= v`, `a > v`, `a >= v`     | `Lt`, `Lte`, `Gt`, `Gte` |
+//! | `a ANY < v` (and `<=`, `>`, `>=`)        | `AnyLt`, ...            |
+//! | `a IN (v, ...)`, `a NOT IN (v, ...)`     | `In`, `NotIn`           |
+//! | `a CONTAINS v`, `a NOT CONTAINS v`       | `Contains`, `NotContains` |
+//! | `a CONTAINS ANY (v, ...)`, `a NOT CONTAINS ANY (v, ...)` | `ContainsAny`, `NotContainsAny` |
+//! | `a GLOB 'p'`, `a NOT GLOB 'p'`           | `Glob`, `NotGlob`       |
+//! | `a IGLOB 'p'`, `a NOT IGLOB 'p'`         | `IGlob`, `NotIGlob`     |
+//! | `a REGEX 'p'`                            | `Regex`                 |
+//! | `a CONTAINS ALL TOKENS 't' [WITH (last_as_prefix = true)]` | `ContainsAllTokens` |
+//! | `a CONTAINS TOKEN SEQUENCE 't'`          | `ContainsTokenSequence` |
+//! | `x AND y`, `x OR y`, `NOT x`             | `And`, `Or`, `Not`      |
+//!
+//! `AND(...)` and `OR(...)` build `And`/`Or` from an explicit list, which is
+//! how `Display` prints combinators with fewer than two children. Keywords are
+//! case-insensitive. Strings are single-quoted (`''` escapes a quote) and
+//! attribute names that aren't plain identifiers are double-quoted. Values are
+//! numbers, strings, `true`, `false`, `null`, `[...]` arrays or JSON objects.
+
+use serde_json::Value;
+use std::fmt;
+
+use super::{ContainsAllTokensParams, Filter};
+use crate::{Error, Result};
+
+const KEYWORDS: &[&str] = &[
+    "AND", "OR", "NOT", "IN", "ANY", "CONTAINS", "ALL", "TOKENS", "TOKEN", "SEQUENCE", "GLOB",
+    "IGLOB", "REGEX", "WITH", "TRUE", "FALSE", "NULL",
+];
+
+impl Filter {
+    /// Parse a filter from its textual syntax. See `Display` for the inverse.
+    ///
+    /// Errors report the 1-based column of the offending token.
+    pub fn parse(input: &str) -> Result<Filter> {
+        let tokens = tokenize(input)?;
+        let mut parser = Parser { tokens, pos: 0, end: input.chars().count() + 1 };
+        let filter = parser.expr()?;
+        match parser.peek() {
+            None => Ok(filter),
+            Some(t) => Err(parse_error(t.column, format!("unexpected {}", t.kind))),
+        }
+    }
+}
+
+impl std::str::FromStr for Filter {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Filter> {
+        Filter::parse(s)
+    }
+}
+
+fn parse_error(column: usize, message: impl Into<String>) -> Error {
+    Error::FilterParse { column, message: message.into() }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum TokenKind {
+    Word(String),
+    QuotedIdent(String),
+    Str(String),
+    Number(serde_json::Number),
+    Object(Value),
+    Op(&'static str),
+    LParen,
+    RParen,
+    LBracket,
+    RBracket,
+    Comma,
+}
+
+impl fmt::Display for TokenKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TokenKind::Word(w) => write!(f, "'{}'", w),
+            TokenKind::QuotedIdent(w) => write!(f, "identifier \"{}\"", w),
+            TokenKind::Str(s) => write!(f, "string '{}'", s),
+            TokenKind::Number(n) => write!(f, "number {}", n),
+            TokenKind::Object(_) => f.write_str("object"),
+            TokenKind::Op(op) => write!(f, "'{}'", op),
+            TokenKind::LParen => f.write_str("'('"),
+            TokenKind::RParen => f.write_str("')'"),
+            TokenKind::LBracket => f.write_str("'['"),
+            TokenKind::RBracket => f.write_str("']'"),
+            TokenKind::Comma => f.write_str("','"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Token {
+    kind: TokenKind,
+    column: usize,
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_' || c == '.'
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>> {
+    let chars: Vec<(usize, char)> = input.char_indices().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let (offset, c) = chars[i];
+        let column = i + 1;
+        let push = |tokens: &mut Vec<Token>, kind| tokens.push(Token { kind, column });
+        match c {
+            c if c.is_whitespace() => i += 1,
+            '(' => {
+                push(&mut tokens, TokenKind::LParen);
+                i += 1;
+            }
+            ')' => {
+                push(&mut tokens, TokenKind::RParen);
+                i += 1;
+            }
+            '[' => {
+                push(&mut tokens, TokenKind::LBracket);
+                i += 1;
+            }
+            ']' => {
+                push(&mut tokens, TokenKind::RBracket);
+                i += 1;
+            }
+            ',' => {
+                push(&mut tokens, TokenKind::Comma);
+                i += 1;
+            }
+            '=' => {
+                push(&mut tokens, TokenKind::Op("="));
+                i += 1;
+            }
+            '!' | '<' | '>' => {
+                let next_is_eq = chars.get(i + 1).map(|(_, c)| *c) == Some('=');
+                let op = match (c, next_is_eq) {
+                    ('!', true) => "!=",
+                    ('<', true) => "<=",
+                    ('>', true) => ">=",
+                    ('<', false) => "<",
+                    ('>', false) => ">",
+                    _ => return Err(parse_error(column, "expected '=' after '!'")),
+                };
+                push(&mut tokens, TokenKind::Op(op));
+                i += if next_is_eq { 2 } else { 1 };
+            }
+            '\'' | '"' => {
+                let quote = c;
+                let mut value = String::new();
+                i += 1;
+                loop {
+                    match chars.get(i) {
+                        None => return Err(parse_error(column, "unterminated quote")),
+                        Some((_, c)) if *c == quote => {
+                            if chars.get(i + 1).map(|(_, c)| *c) == Some(quote) {
+                                value.push(quote);
+                                i += 2;
+                            } else {
+                                i += 1;
+                                break;
+                            }
+                        }
+                        Some((_, c)) => {
+                            value.push(*c);
+                            i += 1;
+                        }
+                    }
+                }
+                let kind = if quote == '\'' { TokenKind::Str(value) } else { TokenKind::QuotedIdent(value) };
+                push(&mut tokens, kind);
+            }
+            '{' => {
+                let mut stream = serde_json::Deserializer::from_str(&input[offset..]).into_iter::<Value>();
+                match stream.next() {
+                    Some(Ok(value)) => {
+                        let end = offset + stream.byte_offset();
+                        push(&mut tokens, TokenKind::Object(value));
+                        while i < chars.len() && chars[i].0 < end {
+                            i += 1;
+                        }
+                    }
+                    _ => return Err(parse_error(column, "invalid JSON object")),
+                }
+            }
+            c if c == '-' || c.is_ascii_digit() => {
+                let start = i;
+                i += 1;
+                while i < chars.len() {
+                    let c = chars[i].1;
+                    let prev = chars[i - 1].1;
+                    let exponent_sign = (c == '-' || c == '+') && (prev == 'e' || prev == 'E');
+                    if c.is_ascii_digit() || c == '.' || c == 'e' || c == 'E' || exponent_sign {
+                        i += 1;
+                    } else {
+                        break;
+                    }
+                }
+                let end = chars.get(i).map(|(o, _)| *o).unwrap_or(input.len());
+                let text = &input[offset..end];
+                match serde_json::from_str::<serde_json::Number>(text) {
+                    Ok(n) => tokens.push(Token { kind: TokenKind::Number(n), column: start + 1 }),
+                    Err(_) => return Err(parse_error(column, format!("invalid number '{}'", text))),
+                }
+            }
+            c if is_word_char(c) => {
+                let start = i;
+                while i < chars.len() && is_word_char(chars[i].1) {
+                    i += 1;
+                }
+                let word: String = chars[start..i].iter().map(|(_, c)| *c).collect();
+                push(&mut tokens, TokenKind::Word(word));
+            }
+            c => return Err(parse_error(column, format!("unexpected character '{}'", c))),
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    end: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn peek_at(&self, offset: usize) -> Option<&Token> {
+        self.tokens.get(self.pos + offset)
+    }
+
+    fn column(&self) -> usize {
+        self.peek().map(|t| t.column).unwrap_or(self.end)
+    }
+
+    fn is_at(&self, offset: usize, kind: &TokenKind) -> bool {
+        self.peek_at(offset).map(|t| &t.kind) == Some(kind)
+    }
+
+    fn eat(&mut self, kind: &TokenKind) -> bool {
+        if self.is_at(0, kind) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn is_keyword_at(&self, offset: usize, keyword: &str) -> bool {
+        match self.peek_at(offset) {
+            Some(Token { kind: TokenKind::Word(w), .. }) => w.eq_ignore_ascii_case(keyword),
+            _ => false,
+        }
+    }
+
+    fn eat_keyword(&mut self, keyword: &str) -> bool {
+        if self.is_keyword_at(0, keyword) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect_keyword(&mut self, keyword: &str) -> Result<()> {
+        if self.eat_keyword(keyword) {
+            Ok(())
+        } else {
+            Err(self.unexpected(&format!("'{}'", keyword)))
+        }
+    }
+
+    fn expect(&mut self, kind: TokenKind) -> Result<()> {
+        if self.eat(&kind) {
+            Ok(())
+        } else {
+            Err(self.unexpected(&kind.to_string()))
+        }
+    }
+
+    fn unexpected(&self, expected: &str) -> Error {
+        match self.peek() {
+            Some(t) => parse_error(t.column, format!("expected {}, found {}", expected, t.kind)),
+            None => parse_error(self.end, format!("expected {}, found end of input", expected)),
+        }
+    }
+
+    fn expr(&mut self) -> Result<Filter> {
+        let mut filters = vec![self.and_expr()?];
+        while self.eat_keyword("OR") {
+            filters.push(self.and_expr()?);
+        }
+        Ok(if filters.len() == 1 { filters.pop().unwrap() } else { Filter::Or(filters) })
+    }
+
+    fn and_expr(&mut self) -> Result<Filter> {
+        let mut filters = vec![self.unary()?];
+        while self.eat_keyword("AND") {
+            filters.push(self.unary()?);
+        }
+        Ok(if filters.len() == 1 { filters.pop().unwrap() } else { Filter::And(filters) })
+    }
+
+    fn unary(&mut self) -> Result<Filter> {
+        if self.eat_keyword("NOT") {
+            return Ok(Filter::Not(Box::new(self.unary()?)));
+        }
+        if self.eat(&TokenKind::LParen) {
+            let filter = self.expr()?;
+            self.expect(TokenKind::RParen)?;
+            return Ok(filter);
+        }
+        for keyword in ["AND", "OR"] {
+            if self.is_keyword_at(0, keyword) && self.is_at(1, &TokenKind::LParen) {
+                self.pos += 2;
+                let mut filters = Vec::new();
+                if !self.is_at(0, &TokenKind::RParen) {
+                    filters.push(self.expr()?);
+                    while self.eat(&TokenKind::Comma) {
+                        filters.push(self.expr()?);
+                    }
+                }
+                self.expect(TokenKind::RParen)?;
+                return Ok(if keyword == "AND" { Filter::And(filters) } else { Filter::Or(filters) });
+            }
+        }
+        self.predicate()
+    }
+
+    fn attribute(&mut self) -> Result<String> {
+        match self.peek().map(|t| t.kind.clone()) {
+            Some(TokenKind::QuotedIdent(name)) => {
+                self.pos += 1;
+                Ok(name)
+            }
+            Some(TokenKind::Word(word)) if !is_keyword(&word) => {
+                self.pos += 1;
+                Ok(word)
+            }
+            _ => Err(self.unexpected("attribute name")),
+        }
+    }
+
+    fn predicate(&mut self) -> Result<Filter> {
+        let attr = self.attribute()?;
+
+        if let Some(Token { kind: TokenKind::Op(op), .. }) = self.peek().cloned() {
+            self.pos += 1;
+            let value = self.value()?;
+            return Ok(match op {
+                "=" => Filter::Eq { attr, value },
+                "!=" => Filter::NotEq { attr, value },
+                "<" => Filter::Lt { attr, value },
+                "<=" => Filter::Lte { attr, value },
+                ">" => Filter::Gt { attr, value },
+                _ => Filter::Gte { attr, value },
+            });
+        }
+
+        if self.eat_keyword("ANY") {
+            let op = match self.peek().map(|t| t.kind.clone()) {
+                Some(TokenKind::Op(op)) if op != "=" && op != "!=" => op,
+                _ => return Err(self.unexpected("'<', '<=', '>' or '>='")),
+            };
+            self.pos += 1;
+            let value = self.value()?;
+            return Ok(match op {
+                "<" => Filter::AnyLt { attr, value },
+                "<=" => Filter::AnyLte { attr, value },
+                ">" => Filter::AnyGt { attr, value },
+                _ => Filter::AnyGte { attr, value },
+            });
+        }
+
+        let negated = self.eat_keyword("NOT");
+
+        if self.eat_keyword("IN") {
+            let values = self.list()?;
+            return Ok(if negated { Filter::NotIn { attr, values } } else { Filter::In { attr, values } });
+        }
+        if self.eat_keyword("GLOB") {
+            let pattern = self.string()?;
+            return Ok(if negated {
+                Filter::NotGlob { attr, pattern }
+            } else {
+                Filter::Glob { attr, pattern }
+            });
+        }
+        if self.eat_keyword("IGLOB") {
+            let pattern = self.string()?;
+            return Ok(if negated {
+                Filter::NotIGlob { attr, pattern }
+            } else {
+                Filter::IGlob { attr, pattern }
+            });
+        }
+        if self.eat_keyword("CONTAINS") {
+            if self.eat_keyword("ANY") {
+                let values = self.list()?;
+                return Ok(if negated {
+                    Filter::NotContainsAny { attr, values }
+                } else {
+                    Filter::ContainsAny { attr, values }
+                });
+            }
+            if negated {
+                let value = self.value()?;
+                return Ok(Filter::NotContains { attr, value });
+            }
+            if self.eat_keyword("ALL") {
+                self.expect_keyword("TOKENS")?;
+                let value = self.string()?;
+                let params = if self.eat_keyword("WITH") { Some(self.token_params()?) } else { None };
+                return Ok(Filter::ContainsAllTokens { attr, value, params });
+            }
+            if self.eat_keyword("TOKEN") {
+                self.expect_keyword("SEQUENCE")?;
+                let value = self.string()?;
+                return Ok(Filter::ContainsTokenSequence { attr, value });
+            }
+            let value = self.value()?;
+            return Ok(Filter::Contains { attr, value });
+        }
+        if !negated && self.eat_keyword("REGEX") {
+            let pattern = self.string()?;
+            return Ok(Filter::Regex { attr, pattern });
+        }
+
+        if negated {
+            Err(self.unexpected("'IN', 'CONTAINS', 'GLOB' or 'IGLOB'"))
+        } else {
+            Err(self.unexpected("operator"))
+        }
+    }
+
+    fn token_params(&mut self) -> Result<ContainsAllTokensParams> {
+        let mut params = ContainsAllTokensParams { last_as_prefix: None };
+        self.expect(TokenKind::LParen)?;
+        while !self.is_at(0, &TokenKind::RParen) {
+            let column = self.column();
+            let key = self.attribute()?;
+            self.expect(TokenKind::Op("="))?;
+            let value = self.value()?;
+            match (key.as_str(), value) {
+                ("last_as_prefix", Value::Bool(b)) => params.last_as_prefix = Some(b),
+                ("last_as_prefix", _) => {
+                    return Err(parse_error(column, "last_as_prefix must be true or false"));
+                }
+                (other, _) => return Err(parse_error(column, format!("unknown parameter '{}'", other))),
+            }
+            if !self.eat(&TokenKind::Comma) {
+                break;
+            }
+        }
+        self.expect(TokenKind::RParen)?;
+        Ok(params)
+    }
+
+    fn string(&mut self) -> Result<String> {
+        match self.peek().map(|t| t.kind.clone()) {
+            Some(TokenKind::Str(s)) => {
+                self.pos += 1;
+                Ok(s)
+            }
+            _ => Err(self.unexpected("string")),
+        }
+    }
+
+    fn list(&mut self) -> Result<Vec<Value>> {
+        self.expect(TokenKind::LParen)?;
+        let values = self.values_until(TokenKind::RParen)?;
+        Ok(values)
+    }
+
+    fn values_until(&mut self, close: TokenKind) -> Result<Vec<Value>> {
+        let mut values = Vec::new();
+        if !self.is_at(0, &close) {
+            values.push(self.value()?);
+            while self.eat(&TokenKind::Comma) {
+                values.push(self.value()?);
+            }
+        }
+        self.expect(close)?;
+        Ok(values)
+    }
+
+    fn value(&mut self) -> Result<Value> {
+        let Some(token) = self.peek().cloned() else {
+            return Err(self.unexpected("value"));
+        };
+        let value = match token.kind {
+            TokenKind::Str(s) => Value::String(s),
+            TokenKind::Number(n) => Value::Number(n),
+            TokenKind::Object(v) => v,
+            TokenKind::LBracket => {
+                self.pos += 1;
+                return Ok(Value::Array(self.values_until(TokenKind::RBracket)?));
+            }
+            TokenKind::Word(w) if w.eq_ignore_ascii_case("true") => Value::Bool(true),
+            TokenKind::Word(w) if w.eq_ignore_ascii_case("false") => Value::Bool(false),
+            TokenKind::Word(w) if w.eq_ignore_ascii_case("null") => Value::Null,
+            _ => return Err(self.unexpected("value")),
+        };
+        self.pos += 1;
+        Ok(value)
+    }
+}
+
+fn is_keyword(word: &str) -> bool {
+    KEYWORDS.iter().any(|k| k.eq_ignore_ascii_case(word))
+}
+
+struct Attr<'a>(&'a str);
+
+impl fmt::Display for Attr<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = self.0;
+        let bare = name.chars().next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+            && name.chars().all(is_word_char)
+            && !is_keyword(name);
+        if bare {
+            f.write_str(name)
+        } else {
+            write!(f, "\"{}\"", name.replace('"', "\"\""))
+        }
+    }
+}
+
+struct Str<'a>(&'a str);
+
+impl fmt::Display for Str<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "'{}'", self.0.replace('\'', "''"))
+    }
+}
+
+struct Val<'a>(&'a Value);
+
+impl fmt::Display for Val<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Value::String(s) => write!(f, "{}", Str(s)),
+            Value::Array(items) => {
+                f.write_str("[")?;
+                write_list(f, items)?;
+                f.write_str("]")
+            }
+            other => write!(f, "{}", other),
+        }
+    }
+}
+
+fn write_list(f: &mut fmt::Formatter<'_>, values: &[Value]) -> fmt::Result {
+    for (i, v) in values.iter().enumerate() {
+        if i > 0 {
+            f.write_str(", ")?;
+        }
+        write!(f, "{}", Val(v))?;
+    }
+    Ok(())
+}
+
+/// Print a child of `And`/`Or`/`Not`, parenthesizing when it would otherwise
+/// bind differently.
+fn write_child(f: &mut fmt::Formatter<'_>, child: &Filter, parent_is_or: bool) -> fmt::Result {
+    let needs_parens = match child {
+        Filter::And(children) => !parent_is_or && children.len() >= 2,
+        Filter::Or(children) => children.len() >= 2,
+        _ => false,
+    };
+    if needs_parens { write!(f, "({})", child) } else { write!(f, "{}", child) }
+}
+
+impl fmt::Display for Filter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Filter::Eq { attr, value } => write!(f, "{} = {}", Attr(attr), Val(value)),
+            Filter::NotEq { attr, value } => write!(f, "{} != {}", Attr(attr), Val(value)),
+            Filter::Lt { attr, value } => write!(f, "{} < {}", Attr(attr), Val(value)),
+            Filter::Lte { attr, value } => write!(f, "{} <= {}", Attr(attr), Val(value)),
+            Filter::Gt { attr, value } => write!(f, "{} > {}", Attr(attr), Val(value)),
+            Filter::Gte { attr, value } => write!(f, "{} >= {}", Attr(attr), Val(value)),
+            Filter::AnyLt { attr, value } => write!(f, "{} ANY < {}", Attr(attr), Val(value)),
+            Filter::AnyLte { attr, value } => write!(f, "{} ANY <= {}", Attr(attr), Val(value)),
+            Filter::AnyGt { attr, value } => write!(f, "{} ANY > {}", Attr(attr), Val(value)),
+            Filter::AnyGte { attr, value } => write!(f, "{} ANY >= {}", Attr(attr), Val(value)),
+            Filter::In { attr, values } => {
+                write!(f, "{} IN (", Attr(attr))?;
+                write_list(f, values)?;
+                f.write_str(")")
+            }
+            Filter::NotIn { attr, values } => {
+                write!(f, "{} NOT IN (", Attr(attr))?;
+                write_list(f, values)?;
+                f.write_str(")")
+            }
+            Filter::Contains { attr, value } => write!(f, "{} CONTAINS {}", Attr(attr), Val(value)),
+            Filter::NotContains { attr, value } => write!(f, "{} NOT CONTAINS {}", Attr(attr), Val(value)),
+            Filter::ContainsAny { attr, values } => {
+                write!(f, "{} CONTAINS ANY (", Attr(attr))?;
+                write_list(f, values)?;
+                f.write_str(")")
+            }
+            Filter::NotContainsAny { attr, values } => {
+                write!(f, "{} NOT CONTAINS ANY (", Attr(attr))?;
+                write_list(f, values)?;
+                f.write_str(")")
+            }
+            Filter::Glob { attr, pattern } => write!(f, "{} GLOB {}", Attr(attr), Str(pattern)),
+            Filter::NotGlob { attr, pattern } => write!(f, "{} NOT GLOB {}", Attr(attr), Str(pattern)),
+            Filter::IGlob { attr, pattern } => write!(f, "{} IGLOB {}", Attr(attr), Str(pattern)),
+            Filter::NotIGlob { attr, pattern } => write!(f, "{} NOT IGLOB {}", Attr(attr), Str(pattern)),
+            Filter::Regex { attr, pattern } => write!(f, "{} REGEX {}", Attr(attr), Str(pattern)),
+            Filter::ContainsAllTokens { attr, value, params } => {
+                write!(f, "{} CONTAINS ALL TOKENS {}", Attr(attr), Str(value))?;
+                match params {
+                    Some(ContainsAllTokensParams { last_as_prefix: Some(v) }) => {
+                        write!(f, " WITH (last_as_prefix = {})", v)
+                    }
+                    Some(ContainsAllTokensParams { last_as_prefix: None }) => f.write_str(" WITH ()"),
+                    None => Ok(()),
+                }
+            }
+            Filter::ContainsTokenSequence { attr, value } => {
+                write!(f, "{} CONTAINS TOKEN SEQUENCE {}", Attr(attr), Str(value))
+            }
+            Filter::And(filters) | Filter::Or(filters) => {
+                let is_or = matches!(self, Filter::Or(_));
+                let keyword = if is_or { "OR" } else { "AND" };
+                if filters.len() < 2 {
+                    write!(f, "{}(", keyword)?;
+                    if let Some(child) = filters.first() {
+                        write!(f, "{}", child)?;
+                    }
+                    return f.write_str(")");
+                }
+                for (i, child) in filters.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, " {} ", keyword)?;
+                    }
+                    // Nested combinators of the same kind keep their grouping.
+                    if std::mem::discriminant(child) == std::mem::discriminant(self) {
+                        write!(f, "({})", child)?;
+                    } else {
+                        write_child(f, child, is_or)?;
+                    }
+                }
+                Ok(())
+            }
+            Filter::Not(filter) => {
+                f.write_str("NOT ")?;
+                write_child(f, filter, false)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn assert_round_trip(filter: Filter) {
+        let text = filter.to_string();
+        assert_eq!(Filter::parse(&text).unwrap(), filter, "round trip of {}", text);
+    }
+
+    #[test]
+    fn test_parse_example() {
+        let f = Filter::parse(
+            "age >= 18 AND (status IN ('active','pending') OR NOT deleted = true) AND name GLOB 'a*'",
+        )
+        .unwrap();
+        assert_eq!(
+            f,
+            Filter::and(vec![
+                Filter::gte("age", 18),
+                Filter::or(vec![
+                    Filter::r#in("status", vec!["active".into(), "pending".into()]),
+                    Filter::not(Filter::eq("deleted", true)),
+                ]),
+                Filter::glob("name", "a*"),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_parse_operators() {
+        assert_eq!(Filter::parse("a != null").unwrap(), Filter::not_eq("a", Value::Null));
+        assert_eq!(Filter::parse("a < -1.5").unwrap(), Filter::lt("a", -1.5));
+        assert_eq!(
+            Filter::parse("scores any <= 3").unwrap(),
+            Filter::AnyLte { attr: "scores".into(), value: json!(3) }
+        );
+        assert_eq!(
+            Filter::parse("tags not contains any ('a', 'b')").unwrap(),
+            Filter::NotContainsAny { attr: "tags".into(), values: vec![json!("a"), json!("b")] }
+        );
+        assert_eq!(Filter::parse("tags CONTAINS 'rust'").unwrap(), Filter::contains("tags", "rust"));
+        assert_eq!(Filter::parse("v = [1, 'x']").unwrap(), Filter::eq("v", json!([1, "x"])));
+        assert_eq!(
+            Filter::parse("text CONTAINS ALL TOKENS 'quick fox' WITH (last_as_prefix = true)").unwrap(),
+            Filter::contains_all_tokens_with_params(
+                "text",
+                "quick fox",
+                ContainsAllTokensParams { last_as_prefix: Some(true) },
+            )
+        );
+        assert_eq!(
+            Filter::parse(r#""first name" = 'O''Brien'"#).unwrap(),
+            Filter::eq("first name", "O'Brien")
+        );
+    }
+
+    #[test]
+    fn test_precedence() {
+        assert_eq!(
+            Filter::parse("a = 1 OR b = 2 AND c = 3").unwrap(),
+            Filter::or(vec![
+                Filter::eq("a", 1),
+                Filter::and(vec![Filter::eq("b", 2), Filter::eq("c", 3)]),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_parse_errors_report_column() {
+        let err = Filter::parse("age >= 18 AND status IN 'active'").unwrap_err();
+        assert!(matches!(err, Error::FilterParse { column: 25, .. }), "{}", err);
+
+        let err = Filter::parse("age >=").unwrap_err();
+        assert!(matches!(err, Error::FilterParse { column: 7, .. }), "{}", err);
+
+        let err = Filter::parse("name = 'unterminated").unwrap_err();
+        assert!(matches!(err, Error::FilterParse { column: 8, .. }), "{}", err);
+
+        let err = Filter::parse("a = 1 b = 2").unwrap_err();
+        assert!(matches!(err, Error::FilterParse { column: 7, .. }), "{}", err);
+    }
+
+    #[test]
+    fn test_display() {
+        let f = Filter::and(vec![
+            Filter::gte("age", 18),
+            Filter::or(vec![Filter::eq("role", "admin"), Filter::not(Filter::eq("deleted", true))]),
+        ]);
+        assert_eq!(f.to_string(), "age >= 18 AND (role = 'admin' OR NOT deleted = true)");
+        assert_eq!(Filter::eq("not", 1).to_string(), r#""not" = 1"#);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        assert_round_trip(Filter::and(vec![
+            Filter::or(vec![
+                Filter::eq("a", 1),
+                Filter::and(vec![Filter::eq("b", 2.5), Filter::eq("c", "x")]),
+            ]),
+            Filter::and(vec![Filter::eq("d", 1), Filter::eq("e", 2)]),
+            Filter::not(Filter::or(vec![Filter::eq("f", Value::Null), Filter::regex("g", "^a'b$")])),
+            Filter::NotIGlob { attr: "h i".into(), pattern: "*X*".into() },
+            Filter::AnyGt { attr: "j".into(), value: json!(-3) },
+            Filter::eq("k", json!({"nested": [1, 2]})),
+            Filter::contains_all_tokens_with_params(
+                "l",
+                "m",
+                ContainsAllTokensParams { last_as_prefix: None },
+            ),
+            Filter::ContainsTokenSequence { attr: "n".into(), value: "o p".into() },
+        ]));
+        assert_round_trip(Filter::and(vec![]));
+        assert_round_trip(Filter::or(vec![Filter::eq("a", 1)]));
+        assert_round_trip(Filter::not(Filter::not(Filter::eq("a", 1))));
+    }
+}
@@ -0,0 +1,495 @@
+use regex::Regex;
+use serde_json::Value;
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+
+use super::Filter;
+use crate::types::parse_rfc3339;
+use crate::{Row, SchemaResponse};
+
+/// A filter prepared for evaluating against many rows, with its regexes
+/// compiled once. Created with `Filter::compile`.
+#[derive(Debug, Clone)]
+pub struct CompiledFilter {
+    filter: Filter,
+    regexes: HashMap<String, Regex>,
+    datetimes: HashSet<String>,
+}
+
+impl CompiledFilter {
+    /// Compare the attributes the schema types as `datetime` or
+    /// `[]datetime` as instants, so `2024-01-15T12:00:00Z` equals
+    /// `2024-01-15T12:00:00.000Z`. Without a schema all strings compare
+    /// bytewise.
+    pub fn with_schema(mut self, schema: &SchemaResponse) -> Self {
+        self.datetimes = schema
+            .0
+            .keys()
+            .filter(|attr| {
+                schema
+                    .attribute(attr)
+                    .and_then(|a| a.attr_type)
+                    .is_some_and(|t| t == "datetime" || t == "[]datetime")
+            })
+            .cloned()
+            .collect();
+        self
+    }
+
+    /// Same as `Filter::matches`, but with datetimes compared as instants
+    /// if a schema was given.
+    pub fn matches(&self, row: &Row) -> bool {
+        self.filter.eval(row, &self.regexes, &self.datetimes)
+    }
+
+    pub fn filter(&self) -> &Filter {
+        &self.filter
+    }
+}
+
+impl Filter {
+    /// Prepare the filter for evaluating against many rows.
+    pub fn compile(&self) -> CompiledFilter {
+        CompiledFilter { filter: self.clone(), regexes: self.regexes(), datetimes: HashSet::new() }
+    }
+
+    /// The filter's valid regex patterns, compiled. Invalid patterns match
+    /// nothing.
+    fn regexes(&self) -> HashMap<String, Regex> {
+        let mut regexes = HashMap::new();
+        let mut stack = vec![self];
+        while let Some(filter) = stack.pop() {
+            match filter {
+                Filter::Regex { pattern, .. } if !regexes.contains_key(pattern) => {
+                    if let Ok(re) = Regex::new(pattern) {
+                        regexes.insert(pattern.clone(), re);
+                    }
+                }
+                Filter::And(filters) | Filter::Or(filters) => stack.extend(filters),
+                Filter::Not(filter) => stack.push(filter),
+                _ => {}
+            }
+        }
+        regexes
+    }
+
+    /// Evaluate the filter against a row client-side.
+    ///
+    /// Follows turbopuffer's semantics: a missing attribute is treated as
+    /// `null`, `Eq null` matches rows without the attribute, and comparisons
+    /// against `null` never match. The negated variants (`NotEq`, `NotIn`,
+    /// `NotContains`, ...) are the exact inverse of their positive forms.
+    ///
+    /// `ContainsAllTokens` and `ContainsTokenSequence` tokenize by lowercasing
+    /// and splitting on non-alphanumeric characters; they don't apply the
+    /// stemming or stopword settings of the attribute's full-text schema.
+    ///
+    /// Strings compare bytewise. To compare datetime attributes as instants,
+    /// pass the namespace schema with `compile(..).with_schema(..)`.
+    ///
+    /// Regexes are compiled on every call; use `compile` to evaluate one
+    /// filter against many rows.
+    pub fn matches(&self, row: &Row) -> bool {
+        self.eval(row, &self.regexes(), &HashSet::new())
+    }
+
+    fn eval(&self, row: &Row, regexes: &HashMap<String, Regex>, datetimes: &HashSet<String>) -> bool {
+        let get = |attr: &str| row.get(attr).unwrap_or(&Value::Null);
+        let instants = |attr: &str| datetimes.contains(attr);
+        let ord = |attr: &str, a: &Value, b: &Value| cmp(a, b, instants(attr));
+        match self {
+            Filter::Eq { attr, value } => eq(get(attr), value, instants(attr)),
+            Filter::NotEq { attr, value } => !eq(get(attr), value, instants(attr)),
+            Filter::Lt { attr, value } => ord(attr, get(attr), value) == Some(Ordering::Less),
+            Filter::Lte { attr, value } => {
+                matches!(ord(attr, get(attr), value), Some(Ordering::Less | Ordering::Equal))
+            }
+            Filter::Gt { attr, value } => ord(attr, get(attr), value) == Some(Ordering::Greater),
+            Filter::Gte { attr, value } => {
+                matches!(ord(attr, get(attr), value), Some(Ordering::Greater | Ordering::Equal))
+            }
+            Filter::AnyLt { attr, value } => any_element(get(attr), |v| {
+                ord(attr, v, value) == Some(Ordering::Less)
+            }),
+            Filter::AnyLte { attr, value } => any_element(get(attr), |v| {
+                matches!(ord(attr, v, value), Some(Ordering::Less | Ordering::Equal))
+            }),
+            Filter::AnyGt { attr, value } => any_element(get(attr), |v| {
+                ord(attr, v, value) == Some(Ordering::Greater)
+            }),
+            Filter::AnyGte { attr, value } => any_element(get(attr), |v| {
+                matches!(ord(attr, v, value), Some(Ordering::Greater | Ordering::Equal))
+            }),
+            Filter::In { attr, values } => is_in(get(attr), values, instants(attr)),
+            Filter::NotIn { attr, values } => !is_in(get(attr), values, instants(attr)),
+            Filter::Contains { attr, value } => contains(get(attr), value, instants(attr)),
+            Filter::NotContains { attr, value } => !contains(get(attr), value, instants(attr)),
+            Filter::ContainsAny { attr, values } => {
+                values.iter().any(|v| contains(get(attr), v, instants(attr)))
+            }
+            Filter::NotContainsAny { attr, values } => {
+                !values.iter().any(|v| contains(get(attr), v, instants(attr)))
+            }
+            Filter::Glob { attr, pattern } => any_string(get(attr), |s| glob_match(pattern, s, false)),
+            Filter::NotGlob { attr, pattern } => !any_string(get(attr), |s| glob_match(pattern, s, false)),
+            Filter::IGlob { attr, pattern } => any_string(get(attr), |s| glob_match(pattern, s, true)),
+            Filter::NotIGlob { attr, pattern } => !any_string(get(attr), |s| glob_match(pattern, s, true)),
+            Filter::Regex { attr, pattern } => {
+                regexes.get(pattern).is_some_and(|re| any_string(get(attr), |s| re.is_match(s)))
+            }
+            Filter::ContainsAllTokens { attr, value, params } => {
+                let last_as_prefix = params.as_ref().and_then(|p| p.last_as_prefix).unwrap_or(false);
+                any_string(get(attr), |s| contains_all_tokens(s, value, last_as_prefix))
+            }
+            Filter::ContainsTokenSequence { attr, value } => {
+                any_string(get(attr), |s| contains_token_sequence(s, value))
+            }
+            Filter::And(filters) => filters.iter().all(|f| f.eval(row, regexes, datetimes)),
+            Filter::Or(filters) => filters.iter().any(|f| f.eval(row, regexes, datetimes)),
+            Filter::Not(filter) => !filter.eval(row, regexes, datetimes),
+        }
+    }
+}
+
+/// Equality with numeric comparison across integer and float representations.
+/// With `instants`, datetime strings are compared as instants.
+fn eq(a: &Value, b: &Value, instants: bool) -> bool {
+    match (a, b) {
+        (Value::Number(_), Value::Number(_)) | (Value::String(_), Value::String(_)) => {
+            cmp(a, b, instants) == Some(Ordering::Equal)
+        }
+        (Value::Array(x), Value::Array(y)) => {
+            x.len() == y.len() && x.iter().zip(y).all(|(a, b)| eq(a, b, instants))
+        }
+        _ => a == b,
+    }
+}
+
+/// Ordering between two non-null values of the same type. Strings compare
+/// bytewise, or as instants when `instants` is set and both parse as RFC 3339
+/// datetimes; arrays compare lexicographically.
+fn cmp(a: &Value, b: &Value, instants: bool) -> Option<Ordering> {
+    match (a, b) {
+        (Value::Number(x), Value::Number(y)) => {
+            if let (Some(x), Some(y)) = (x.as_u64(), y.as_u64()) {
+                Some(x.cmp(&y))
+            } else if let (Some(x), Some(y)) = (x.as_i64(), y.as_i64()) {
+                Some(x.cmp(&y))
+            } else {
+                x.as_f64()?.partial_cmp(&y.as_f64()?)
+            }
+        }
+        (Value::String(x), Value::String(y)) => {
+            if instants && let (Some(x), Some(y)) = (parse_rfc3339(x), parse_rfc3339(y)) {
+                return Some(x.cmp(&y));
+            }
+            Some(x.as_bytes().cmp(y.as_bytes()))
+        }
+        (Value::Bool(x), Value::Bool(y)) => Some(x.cmp(y)),
+        (Value::Array(x), Value::Array(y)) => {
+            for (a, b) in x.iter().zip(y) {
+                match cmp(a, b, instants)? {
+                    Ordering::Equal => continue,
+                    other => return Some(other),
+                }
+            }
+            Some(x.len().cmp(&y.len()))
+        }
+        _ => None,
+    }
+}
+
+fn any_element(value: &Value, f: impl Fn(&Value) -> bool) -> bool {
+    match value {
+        Value::Array(items) => items.iter().any(f),
+        _ => false,
+    }
+}
+
+/// Apply `f` to a string attribute, or to each element of a `[]string` attribute.
+fn any_string(value: &Value, f: impl Fn(&str) -> bool) -> bool {
+    match value {
+        Value::String(s) => f(s),
+        Value::Array(items) => items.iter().any(|v| v.as_str().is_some_and(&f)),
+        _ => false,
+    }
+}
+
+/// For array attributes, `In` checks whether the two sets intersect.
+fn is_in(value: &Value, values: &[Value], instants: bool) -> bool {
+    match value {
+        Value::Array(items) => items.iter().any(|item| values.iter().any(|v| eq(item, v, instants))),
+        _ => values.iter().any(|v| eq(value, v, instants)),
+    }
+}
+
+fn contains(value: &Value, needle: &Value, instants: bool) -> bool {
+    match value {
+        Value::Array(items) => items.iter().any(|item| eq(item, needle, instants)),
+        _ => false,
+    }
+}
+
+/// Unix-style glob matching supporting `*`, `?`, `[...]` classes (with `!` or
+/// `^` negation and ranges) and `\\` escapes.
+fn glob_match(pattern: &str, text: &str, case_insensitive: bool) -> bool {
+    let fold = |s: &str| -> Vec<char> {
+        if case_insensitive {
+            s.chars().flat_map(char::to_lowercase).collect()
+        } else {
+            s.chars().collect()
+        }
+    };
+    let pattern = fold(pattern);
+    let text = fold(text);
+
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+    while t < text.len() {
+        if p < pattern.len() {
+            match pattern[p] {
+                '*' => {
+                    backtrack = Some((p, t));
+                    p += 1;
+                    continue;
+                }
+                '?' => {
+                    p += 1;
+                    t += 1;
+                    continue;
+                }
+                '[' => {
+                    if let Some((matched, len)) = match_class(&pattern[p..], text[t]) {
+                        if matched {
+                            p += len;
+                            t += 1;
+                            continue;
+                        }
+                    } else if text[t] == '[' {
+                        p += 1;
+                        t += 1;
+                        continue;
+                    }
+                }
+                '\\' if p + 1 < pattern.len() => {
+                    if pattern[p + 1] == text[t] {
+                        p += 2;
+                        t += 1;
+                        continue;
+                    }
+                }
+                c => {
+                    if c == text[t] {
+                        p += 1;
+                        t += 1;
+                        continue;
+                    }
+                }
+            }
+        }
+        match backtrack {
+            Some((star, matched)) => {
+                p = star + 1;
+                t = matched + 1;
+                backtrack = Some((star, matched + 1));
+            }
+            None => return false,
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+/// Match a `[...]` class at the start of `pattern`. Returns whether `c`
+/// matched and the length of the class, or `None` if the class is unterminated.
+fn match_class(pattern: &[char], c: char) -> Option<(bool, usize)> {
+    let mut i = 1;
+    let negated = matches!(pattern.get(i), Some('!' | '^'));
+    if negated {
+        i += 1;
+    }
+    let mut matched = false;
+    let mut first = true;
+    while i < pattern.len() {
+        if pattern[i] == ']' && !first {
+            return Some((matched != negated, i + 1));
+        }
+        first = false;
+        let lo = pattern[i];
+        if i + 2 < pattern.len() && pattern[i + 1] == '-' && pattern[i + 2] != ']' {
+            let hi = pattern[i + 2];
+            if lo <= c && c <= hi {
+                matched = true;
+            }
+            i += 3;
+        } else {
+            if lo == c {
+                matched = true;
+            }
+            i += 1;
+        }
+    }
+    None
+}
+
+fn tokenize(s: &str) -> Vec<String> {
+    s.split(|c: char| !c.is_alphanumeric())
+        .filter(|t| !t.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+fn contains_all_tokens(text: &str, query: &str, last_as_prefix: bool) -> bool {
+    let doc = tokenize(text);
+    let query = tokenize(query);
+    query.iter().enumerate().all(|(i, token)| {
+        if last_as_prefix && i == query.len() - 1 {
+            doc.iter().any(|d| d.starts_with(token.as_str()))
+        } else {
+            doc.contains(token)
+        }
+    })
+}
+
+fn contains_token_sequence(text: &str, query: &str) -> bool {
+    let doc = tokenize(text);
+    let query = tokenize(query);
+    query.is_empty() || doc.windows(query.len()).any(|w| w == query.as_slice())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ContainsAllTokensParams;
+    use serde_json::json;
+
+    fn row(value: Value) -> Row {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn test_eq_and_null_handling() {
+        let r = row(json!({"id": 1, "name": "alice", "maybe": null}));
+        assert!(Filter::eq("name", "alice").matches(&r));
+        assert!(!Filter::eq("name", "bob").matches(&r));
+        assert!(Filter::eq("maybe", Value::Null).matches(&r));
+        assert!(Filter::eq("missing", Value::Null).matches(&r));
+        assert!(!Filter::not_eq("missing", Value::Null).matches(&r));
+        assert!(Filter::not_eq("name", Value::Null).matches(&r));
+        assert!(Filter::not_eq("missing", "alice").matches(&r));
+        assert!(Filter::eq("id", 1.0).matches(&r));
+    }
+
+    #[test]
+    fn test_comparisons() {
+        let r = row(json!({"age": 30, "name": "bob", "ts": "2024-01-15T12:00:00.000Z"}));
+        assert!(Filter::gte("age", 30).matches(&r));
+        assert!(Filter::gt("age", 29.5).matches(&r));
+        assert!(!Filter::lt("age", 30).matches(&r));
+        assert!(Filter::lte("name", "bob").matches(&r));
+        assert!(Filter::lt("ts", "2024-02-01T00:00:00.000Z").matches(&r));
+        assert!(!Filter::lt("missing", 100).matches(&r));
+        assert!(!Filter::gt("name", 1).matches(&r));
+    }
+
+    #[test]
+    fn test_any_comparisons() {
+        let r = row(json!({"scores": [1, 5, 9]}));
+        let any = |f: fn(String, Value) -> Filter, v: i64| f("scores".to_string(), json!(v)).matches(&r);
+        assert!(any(|attr, value| Filter::AnyLt { attr, value }, 2));
+        assert!(!any(|attr, value| Filter::AnyLt { attr, value }, 1));
+        assert!(any(|attr, value| Filter::AnyLte { attr, value }, 1));
+        assert!(any(|attr, value| Filter::AnyGt { attr, value }, 8));
+        assert!(!any(|attr, value| Filter::AnyGte { attr, value }, 10));
+    }
+
+    #[test]
+    fn test_in_and_contains() {
+        let r = row(json!({"status": "active", "numbers": [1, 2, 3], "tags": ["rust", "go"]}));
+        assert!(Filter::r#in("status", vec!["active".into(), "pending".into()]).matches(&r));
+        assert!(Filter::not_in("status", vec!["deleted".into()]).matches(&r));
+        assert!(Filter::r#in("numbers", vec![2.into(), 4.into()]).matches(&r));
+        assert!(!Filter::r#in("numbers", vec![7.into()]).matches(&r));
+        assert!(Filter::contains("tags", "rust").matches(&r));
+        assert!(!Filter::NotContains { attr: "tags".into(), value: json!("go") }.matches(&r));
+        assert!(Filter::contains_any("tags", vec!["python".into(), "go".into()]).matches(&r));
+        assert!(Filter::NotContainsAny { attr: "tags".into(), values: vec![json!("c")] }.matches(&r));
+        assert!(!Filter::contains("status", "active").matches(&r));
+    }
+
+    #[test]
+    fn test_glob_and_regex() {
+        let r = row(json!({"name": "Alice", "email": "alice@example.com"}));
+        assert!(Filter::glob("name", "A*").matches(&r));
+        assert!(!Filter::glob("name", "a*").matches(&r));
+        assert!(Filter::iglob("name", "a*E").matches(&r));
+        assert!(Filter::glob("name", "?lic[a-e]").matches(&r));
+        assert!(Filter::glob("name", "[!B]*").matches(&r));
+        assert!(Filter::NotGlob { attr: "name".into(), pattern: "B*".into() }.matches(&r));
+        assert!(!Filter::NotIGlob { attr: "name".into(), pattern: "*LICE".into() }.matches(&r));
+        assert!(Filter::regex("email", r".*@example\.com$").matches(&r));
+        assert!(!Filter::regex("email", "(unclosed").matches(&r));
+    }
+
+    #[test]
+    fn test_compiled_filter() {
+        let f = Filter::regex("email", r"@example\.com$") & !Filter::regex("name", "^B") | Filter::regex("x", "(");
+        let compiled = f.compile();
+        assert_eq!(compiled.regexes.len(), 2);
+        for r in [json!({"name": "Alice", "email": "a@example.com"}), json!({"name": "Bob", "email": "b@example.com"})] {
+            let r = row(r);
+            assert_eq!(compiled.matches(&r), f.matches(&r));
+        }
+        assert!(compiled.matches(&row(json!({"name": "Alice", "email": "a@example.com"}))));
+    }
+
+    #[test]
+    fn test_datetimes_compare_as_instants_with_schema() {
+        let r = row(json!({"ts": "2024-01-15T12:00:00Z", "label": "2024-01-15T12:00:00Z"}));
+        assert!(!Filter::eq("ts", "2024-01-15T12:00:00.000Z").matches(&r));
+
+        let schema = SchemaResponse(HashMap::from([("ts".to_string(), json!("datetime"))]));
+        let compiled = |f: Filter| f.compile().with_schema(&schema).matches(&r);
+        assert!(compiled(Filter::eq("ts", "2024-01-15T12:00:00.000Z")));
+        assert!(compiled(Filter::lte("ts", "2024-01-15T12:00:00.000Z")));
+        assert!(compiled(Filter::gt("ts", "2024-01-15T11:59:59.999999Z")));
+        assert!(compiled(Filter::lt("ts", "2024-01-15T13:00:00+00:30")));
+        assert!(!compiled(Filter::eq("ts", "2024-01-15")));
+        assert!(!compiled(Filter::eq("label", "2024-01-15T12:00:00.000Z")));
+    }
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("*", "", false));
+        assert!(glob_match("a*b*c", "axxbyyc", false));
+        assert!(!glob_match("a*b*c", "axxbyy", false));
+        assert!(glob_match(r"a\*", "a*", false));
+        assert!(!glob_match(r"a\*", "ab", false));
+    }
+
+    #[test]
+    fn test_token_filters() {
+        let r = row(json!({"text": "Walruses are large marine mammals, with long tusks"}));
+        assert!(Filter::contains_all_tokens("text", "marine MAMMALS").matches(&r));
+        assert!(!Filter::contains_all_tokens("text", "marine mammals short").matches(&r));
+        assert!(Filter::contains_all_tokens_with_params(
+            "text",
+            "long tus",
+            ContainsAllTokensParams { last_as_prefix: Some(true) },
+        )
+        .matches(&r));
+        let seq = |value: &str| Filter::ContainsTokenSequence { attr: "text".into(), value: value.into() };
+        assert!(seq("large marine").matches(&r));
+        assert!(!seq("marine large").matches(&r));
+    }
+
+    #[test]
+    fn test_logical_ops() {
+        let r = row(json!({"a": 1, "b": 2}));
+        assert!(Filter::and(vec![Filter::eq("a", 1), Filter::eq("b", 2)]).matches(&r));
+        assert!(!Filter::and(vec![Filter::eq("a", 1), Filter::eq("b", 3)]).matches(&r));
+        assert!(Filter::or(vec![Filter::eq("a", 5), Filter::eq("b", 2)]).matches(&r));
+        assert!(Filter::not(Filter::eq("a", 2)).matches(&r));
+        assert!(Filter::and(vec![]).matches(&r));
+        assert!(!Filter::or(vec![]).matches(&r));
+    }
+}
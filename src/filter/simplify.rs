@@ -0,0 +1,386 @@
+use serde_json::Value;
+
+use super::Filter;
+use crate::Row;
+use crate::types::parse_rfc3339;
+
+impl Filter {
+    /// Normalize the filter into an equivalent, usually smaller one.
+    ///
+    /// - nested `And`/`Or` are flattened and duplicate children removed
+    /// - `Not(Not(x))` folds to `x`, and `Not` is pushed into leaf operators
+    ///   that have a negated form (`Eq` -> `NotEq`, `In` -> `NotIn`, `Glob` ->
+    ///   `NotGlob`, ...)
+    /// - `Eq`s with scalar, non-null values and `In`s on the same attribute
+    ///   inside an `Or` merge into one `In`
+    /// - inside an `And`, an `Eq` makes any other comparison, `In`, glob or
+    ///   regex on the same attribute either redundant (dropped) or
+    ///   contradictory, when its operands have the same JSON type as the
+    ///   `Eq` value and none of them look like datetimes
+    ///
+    /// A filter that can never match simplifies to `Or([])`, and one that
+    /// always matches to `And([])`; callers can check for these to skip a
+    /// request entirely.
+    pub fn simplify(&self) -> Filter {
+        match self {
+            Filter::And(filters) => simplify_and(filters),
+            Filter::Or(filters) => simplify_or(filters),
+            Filter::Not(filter) => simplify_not(filter.simplify()),
+            leaf => leaf.clone(),
+        }
+    }
+
+    /// True for `Or([])`, the simplified form of a filter that never matches.
+    pub fn is_contradiction(&self) -> bool {
+        matches!(self, Filter::Or(filters) if filters.is_empty())
+    }
+
+    /// True for `And([])`, the simplified form of a filter that always matches.
+    pub fn is_tautology(&self) -> bool {
+        matches!(self, Filter::And(filters) if filters.is_empty())
+    }
+
+    fn attr(&self) -> Option<&str> {
+        match self {
+            Filter::Eq { attr, .. }
+            | Filter::NotEq { attr, .. }
+            | Filter::Lt { attr, .. }
+            | Filter::Lte { attr, .. }
+            | Filter::Gt { attr, .. }
+            | Filter::Gte { attr, .. }
+            | Filter::AnyLt { attr, .. }
+            | Filter::AnyLte { attr, .. }
+            | Filter::AnyGt { attr, .. }
+            | Filter::AnyGte { attr, .. }
+            | Filter::In { attr, .. }
+            | Filter::NotIn { attr, .. }
+            | Filter::Contains { attr, .. }
+            | Filter::NotContains { attr, .. }
+            | Filter::ContainsAny { attr, .. }
+            | Filter::NotContainsAny { attr, .. }
+            | Filter::Glob { attr, .. }
+            | Filter::NotGlob { attr, .. }
+            | Filter::IGlob { attr, .. }
+            | Filter::NotIGlob { attr, .. }
+            | Filter::Regex { attr, .. }
+            | Filter::ContainsAllTokens { attr, .. }
+            | Filter::ContainsTokenSequence { attr, .. } => Some(attr),
+            Filter::And(_) | Filter::Or(_) | Filter::Not(_) => None,
+        }
+    }
+}
+
+/// The negated form of a leaf operator, if it has one.
+fn negate(filter: &Filter) -> Option<Filter> {
+    let negated = match filter.clone() {
+        Filter::Eq { attr, value } => Filter::NotEq { attr, value },
+        Filter::NotEq { attr, value } => Filter::Eq { attr, value },
+        Filter::In { attr, values } => Filter::NotIn { attr, values },
+        Filter::NotIn { attr, values } => Filter::In { attr, values },
+        Filter::Contains { attr, value } => Filter::NotContains { attr, value },
+        Filter::NotContains { attr, value } => Filter::Contains { attr, value },
+        Filter::ContainsAny { attr, values } => Filter::NotContainsAny { attr, values },
+        Filter::NotContainsAny { attr, values } => Filter::ContainsAny { attr, values },
+        Filter::Glob { attr, pattern } => Filter::NotGlob { attr, pattern },
+        Filter::NotGlob { attr, pattern } => Filter::Glob { attr, pattern },
+        Filter::IGlob { attr, pattern } => Filter::NotIGlob { attr, pattern },
+        Filter::NotIGlob { attr, pattern } => Filter::IGlob { attr, pattern },
+        _ => return None,
+    };
+    Some(negated)
+}
+
+fn simplify_not(inner: Filter) -> Filter {
+    match inner {
+        Filter::Not(x) => *x,
+        Filter::And(filters) if filters.is_empty() => Filter::Or(vec![]),
+        Filter::Or(filters) if filters.is_empty() => Filter::And(vec![]),
+        other => match negate(&other) {
+            Some(negated) => negated,
+            None => Filter::Not(Box::new(other)),
+        },
+    }
+}
+
+fn is_complement(a: &Filter, b: &Filter) -> bool {
+    match b {
+        Filter::Not(inner) => **inner == *a,
+        _ => negate(a).as_ref() == Some(b),
+    }
+}
+
+fn has_complement_pair(filters: &[Filter]) -> bool {
+    filters
+        .iter()
+        .enumerate()
+        .any(|(i, a)| filters[i + 1..].iter().any(|b| is_complement(a, b) || is_complement(b, a)))
+}
+
+/// Simplify each child and splice in children of nested combinators of the same kind.
+fn flatten(filters: &[Filter], is_and: bool) -> Vec<Filter> {
+    let mut flat: Vec<Filter> = Vec::with_capacity(filters.len());
+    for child in filters {
+        let simplified = child.simplify();
+        let nested = match simplified {
+            Filter::And(children) if is_and => children,
+            Filter::Or(children) if !is_and => children,
+            other => vec![other],
+        };
+        for f in nested {
+            if !flat.contains(&f) {
+                flat.push(f);
+            }
+        }
+    }
+    flat
+}
+
+fn simplify_and(filters: &[Filter]) -> Filter {
+    let mut filters = flatten(filters, true);
+    if filters.iter().any(Filter::is_contradiction) || has_complement_pair(&filters) {
+        return Filter::Or(vec![]);
+    }
+
+    // An `Eq` pins the attribute to one value, so every other condition on that
+    // attribute can be decided by evaluating it against that value.
+    let pins: Vec<Filter> = filters.iter().filter(|f| matches!(f, Filter::Eq { .. })).cloned().collect();
+    for pin in pins {
+        let Filter::Eq { attr, value } = &pin else { unreachable!() };
+        // Already dropped as implied by an earlier, numerically equal `Eq`.
+        if !filters.contains(&pin) {
+            continue;
+        }
+        let pinned: Row = [(attr.clone(), value.clone())].into_iter().collect();
+        let mut contradiction = false;
+        filters.retain(|f| {
+            if *f == pin || f.attr() != Some(attr.as_str()) || !decidable(f, value) {
+                return true;
+            }
+            contradiction |= !f.matches(&pinned);
+            false
+        });
+        if contradiction {
+            return Filter::Or(vec![]);
+        }
+    }
+
+    match filters.len() {
+        1 => filters.pop().unwrap(),
+        _ => Filter::And(filters),
+    }
+}
+
+/// Whether evaluating `filter` client-side against an attribute pinned to
+/// `pin` gives the server's answer.
+///
+/// Operands of another JSON type depend on how the server coerces them, and
+/// strings that parse as RFC 3339 may be datetimes, compared as instants, or
+/// plain strings, compared bytewise, depending on the schema. Element
+/// operators depend on whether the attribute is an array, and token filters
+/// on its tokenizer settings, which the evaluator only approximates.
+fn decidable(filter: &Filter, pin: &Value) -> bool {
+    let operands: Vec<&Value> = match filter {
+        Filter::Eq { value, .. }
+        | Filter::NotEq { value, .. }
+        | Filter::Lt { value, .. }
+        | Filter::Lte { value, .. }
+        | Filter::Gt { value, .. }
+        | Filter::Gte { value, .. } => vec![value],
+        Filter::In { values, .. } | Filter::NotIn { values, .. } => values.iter().collect(),
+        Filter::Glob { .. }
+        | Filter::NotGlob { .. }
+        | Filter::IGlob { .. }
+        | Filter::NotIGlob { .. }
+        | Filter::Regex { .. } => return pin.is_string() && !is_datetime(pin),
+        _ => return false,
+    };
+    !is_datetime(pin) && operands.into_iter().all(|v| same_type(v, pin) && !is_datetime(v))
+}
+
+fn same_type(a: &Value, b: &Value) -> bool {
+    std::mem::discriminant(a) == std::mem::discriminant(b)
+}
+
+fn is_datetime(value: &Value) -> bool {
+    value.as_str().is_some_and(|s| parse_rfc3339(s).is_some())
+}
+
+fn simplify_or(filters: &[Filter]) -> Filter {
+    let filters = flatten(filters, false);
+    if filters.iter().any(Filter::is_tautology) || has_complement_pair(&filters) {
+        return Filter::And(vec![]);
+    }
+
+    // Merge `Eq`s and `In`s on the same attribute into a single `In`, kept at
+    // the position of the first one.
+    let mut merged: Vec<Filter> = Vec::with_capacity(filters.len());
+    for filter in filters {
+        let attr = match &filter {
+            Filter::Eq { attr, value } if mergeable(value) => attr.clone(),
+            Filter::In { attr, .. } => attr.clone(),
+            _ => {
+                merged.push(filter);
+                continue;
+            }
+        };
+        let existing = merged.iter_mut().find(|f| match f {
+            Filter::Eq { attr: a, value } => *a == attr && mergeable(value),
+            Filter::In { attr: a, .. } => *a == attr,
+            _ => false,
+        });
+        let Some(existing) = existing else {
+            merged.push(filter);
+            continue;
+        };
+        let mut all = in_values(std::mem::replace(existing, Filter::And(vec![])));
+        for v in in_values(filter) {
+            if !all.contains(&v) {
+                all.push(v);
+            }
+        }
+        *existing = Filter::In { attr, values: all };
+    }
+
+    match merged.len() {
+        1 => merged.pop().unwrap(),
+        _ => Filter::Or(merged),
+    }
+}
+
+fn in_values(filter: Filter) -> Vec<Value> {
+    match filter {
+        Filter::Eq { value, .. } => vec![value],
+        Filter::In { values, .. } => values,
+        _ => unreachable!("only Eq and In are merged"),
+    }
+}
+
+/// Only scalar, non-null `Eq` values merge: `Eq null` matches missing
+/// attributes, and an array value compares a whole array attribute, while
+/// `In` matches any of its elements.
+fn mergeable(value: &Value) -> bool {
+    !value.is_null() && !value.is_array() && !value.is_object()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_flatten_and_dedupe() {
+        let f = Filter::and(vec![
+            Filter::eq("a", 1),
+            Filter::and(vec![Filter::gt("b", 2), Filter::and(vec![Filter::gt("b", 2), Filter::lt("c", 3)])]),
+        ]);
+        assert_eq!(
+            f.simplify(),
+            Filter::and(vec![Filter::eq("a", 1), Filter::gt("b", 2), Filter::lt("c", 3)])
+        );
+        assert_eq!(Filter::or(vec![Filter::eq("a", 1), Filter::eq("a", 1)]).simplify(), Filter::eq("a", 1));
+    }
+
+    #[test]
+    fn test_not_folding() {
+        assert_eq!(Filter::not(Filter::not(Filter::regex("a", "x"))).simplify(), Filter::regex("a", "x"));
+        assert_eq!(Filter::not(Filter::eq("a", 1)).simplify(), Filter::not_eq("a", 1));
+        assert_eq!(
+            Filter::not(Filter::r#in("a", vec![1.into()])).simplify(),
+            Filter::not_in("a", vec![1.into()])
+        );
+        assert_eq!(Filter::not(Filter::glob("a", "x*")).simplify(), Filter::not_glob("a", "x*"));
+        assert_eq!(Filter::not(Filter::not_iglob("a", "x*")).simplify(), Filter::iglob("a", "x*"));
+        assert_eq!(
+            Filter::not(Filter::regex("a", "x")).simplify(),
+            Filter::not(Filter::regex("a", "x"))
+        );
+    }
+
+    #[test]
+    fn test_merge_eq_into_in() {
+        let f = Filter::or(vec![
+            Filter::eq("status", "active"),
+            Filter::gt("age", 18),
+            Filter::eq("status", "pending"),
+            Filter::r#in("status", vec!["active".into(), "archived".into()]),
+        ]);
+        assert_eq!(
+            f.simplify(),
+            Filter::or(vec![
+                Filter::r#in("status", vec!["active".into(), "pending".into(), "archived".into()]),
+                Filter::gt("age", 18),
+            ])
+        );
+
+        // Null matches missing attributes, and an array value compares the
+        // whole array, so neither is merged.
+        let f = Filter::or(vec![Filter::eq("a", Value::Null), Filter::eq("a", 1)]);
+        assert_eq!(f.simplify(), f);
+        let f = Filter::or(vec![
+            Filter::eq("tags", serde_json::json!(["a", "b"])),
+            Filter::eq("tags", serde_json::json!(["c"])),
+        ]);
+        assert_eq!(f.simplify(), f);
+    }
+
+    #[test]
+    fn test_contradictions() {
+        assert!(Filter::and(vec![Filter::eq("a", 1), Filter::eq("a", 2)]).simplify().is_contradiction());
+        assert!(Filter::and(vec![Filter::eq("a", 1), Filter::not_eq("a", 1)]).simplify().is_contradiction());
+        assert!(Filter::and(vec![Filter::eq("a", 5), Filter::lt("a", 3)]).simplify().is_contradiction());
+        assert!(
+            Filter::and(vec![Filter::regex("a", "x"), Filter::not(Filter::regex("a", "x"))])
+                .simplify()
+                .is_contradiction()
+        );
+        assert!(
+            Filter::and(vec![Filter::eq("b", 1), Filter::or(vec![])]).simplify().is_contradiction()
+        );
+        assert!(Filter::or(vec![Filter::glob("a", "x"), Filter::not_glob("a", "x")]).simplify().is_tautology());
+    }
+
+    #[test]
+    fn test_eq_implies_other_conditions() {
+        let f = Filter::and(vec![
+            Filter::r#in("a", vec![1.into(), 2.into()]),
+            Filter::eq("b", "x"),
+            Filter::eq("a", 1),
+            Filter::gte("a", 0),
+        ]);
+        assert_eq!(f.simplify(), Filter::and(vec![Filter::eq("b", "x"), Filter::eq("a", 1)]));
+    }
+
+    #[test]
+    fn test_undecidable_conditions_are_kept() {
+        let f = Filter::eq("ts", "2024-01-15T12:00:00Z") & Filter::lte("ts", "2024-01-15T12:00:00.000Z");
+        assert_eq!(f.simplify(), f);
+        let f = Filter::eq("ts", "2024-01-15T12:00:00Z") & Filter::eq("ts", "2024-01-15T12:00:00.000Z");
+        assert_eq!(f.simplify(), f);
+
+        let f = Filter::eq("a", 1) & Filter::lt("a", "x");
+        assert_eq!(f.simplify(), f);
+        let f = Filter::eq("a", 1) & Filter::glob("a", "1*");
+        assert_eq!(f.simplify(), f);
+        let f = Filter::eq("tags", "a") & Filter::contains("tags", "b");
+        assert_eq!(f.simplify(), f);
+
+        assert!((Filter::eq("name", "bob") & Filter::glob("name", "a*")).simplify().is_contradiction());
+    }
+
+    #[test]
+    fn test_simplify_preserves_semantics() {
+        let f = Filter::or(vec![
+            Filter::and(vec![Filter::eq("a", 1), Filter::not(Filter::not(Filter::gt("b", 2)))]),
+            Filter::eq("a", 3),
+            Filter::not(Filter::r#in("c", vec!["x".into()])),
+        ]);
+        let simplified = f.simplify();
+        for a in 0..5 {
+            for b in 0..5 {
+                for c in ["x", "y"] {
+                    let row: Row = serde_json::from_value(serde_json::json!({"a": a, "b": b, "c": c})).unwrap();
+                    assert_eq!(f.matches(&row), simplified.matches(&row), "{} vs {}", f, simplified);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,260 @@
+use futures::future::{Either, select};
+use std::collections::VecDeque;
+use std::future::Future;
+use std::pin::pin;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::Result;
+
+/// Latencies kept for estimating the hedge delay.
+const LATENCY_WINDOW: usize = 1000;
+
+/// Latencies needed before the percentile replaces `min_delay`.
+const MIN_SAMPLES: usize = 20;
+
+/// When `query` and `multi_query` send a duplicate request, enabled with
+/// `Client::with_hedging`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HedgingPolicy {
+    /// A duplicate is sent once a request has taken longer than this
+    /// percentile of recent latencies, e.g. `0.95`.
+    pub percentile: f64,
+    /// The shortest hedge delay, also used until enough latencies are seen.
+    pub min_delay: Duration,
+    /// The most requests hedged, as a fraction of all requests. A hedge
+    /// counts once it is sent, not while it waits on rate or concurrency
+    /// limits.
+    ///
+    /// Only the winning response's billed bytes are charged to byte rate
+    /// limits; the cancelled request's billing is never seen.
+    pub max_hedge_ratio: f64,
+}
+
+impl Default for HedgingPolicy {
+    fn default() -> Self {
+        HedgingPolicy { percentile: 0.95, min_delay: Duration::from_millis(50), max_hedge_ratio: 0.05 }
+    }
+}
+
+tokio::task_local! {
+    /// Set while a hedge runs, so the client can count it against the
+    /// budget once it is sent.
+    static HEDGE: ();
+}
+
+/// Whether the current request is a hedge.
+pub(crate) fn is_hedge() -> bool {
+    HEDGE.try_with(|_| ()).is_ok()
+}
+
+#[derive(Default)]
+struct HedgeState {
+    latencies: VecDeque<Duration>,
+    requests: u64,
+    hedged: u64,
+}
+
+pub(crate) struct Hedger {
+    policy: HedgingPolicy,
+    state: Mutex<HedgeState>,
+}
+
+impl Hedger {
+    pub(crate) fn new(policy: HedgingPolicy) -> Self {
+        Hedger { policy, state: Mutex::new(HedgeState::default()) }
+    }
+
+    fn delay(&self) -> Duration {
+        let state = self.state.lock().unwrap();
+        if state.latencies.len() < MIN_SAMPLES {
+            return self.policy.min_delay;
+        }
+        let mut sorted: Vec<Duration> = state.latencies.iter().copied().collect();
+        sorted.sort();
+        let i = ((sorted.len() as f64 * self.policy.percentile).ceil() as usize).clamp(1, sorted.len()) - 1;
+        sorted[i].max(self.policy.min_delay)
+    }
+
+    fn record(&self, latency: Duration) {
+        let mut state = self.state.lock().unwrap();
+        if state.latencies.len() == LATENCY_WINDOW {
+            state.latencies.pop_front();
+        }
+        state.latencies.push_back(latency);
+    }
+
+    /// Whether another request may be hedged within the budget.
+    fn may_hedge(&self) -> bool {
+        let state = self.state.lock().unwrap();
+        (state.hedged + 1) as f64 <= self.policy.max_hedge_ratio * state.requests as f64
+    }
+
+    /// Count a hedge against the budget as it is sent.
+    pub(crate) fn sent_hedge(&self) {
+        self.state.lock().unwrap().hedged += 1;
+    }
+
+    /// Run one attempt, recording its latency if it succeeds. Failures
+    /// often return early, so they would skew the hedge delay down.
+    async fn timed<T>(&self, attempt: impl Future<Output = Result<T>>) -> Result<T> {
+        let started = Instant::now();
+        let result = attempt.await;
+        if result.is_ok() {
+            self.record(started.elapsed());
+        }
+        result
+    }
+
+    /// Run `request`, sending a duplicate if it is slower than the hedge
+    /// delay and the budget allows. The first success wins and the other
+    /// request is dropped; an error only wins if both fail. The duplicate
+    /// runs with `is_hedge` set.
+    pub(crate) async fn run<T, F, Fut>(&self, request: F) -> Result<T>
+    where
+        F: Fn() -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        self.state.lock().unwrap().requests += 1;
+        let mut primary = pin!(self.timed(request()));
+
+        match select(primary.as_mut(), pin!(tokio::time::sleep(self.delay()))).await {
+            Either::Left((result, _)) => result,
+            Either::Right(((), _)) if !self.may_hedge() => primary.await,
+            Either::Right(((), _)) => match select(primary, pin!(HEDGE.scope((), self.timed(request())))).await {
+                Either::Left((Err(_), hedge)) => hedge.await,
+                Either::Right((Err(_), primary)) => primary.await,
+                Either::Left((result, _)) | Either::Right((result, _)) => result,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Error;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn policy(max_hedge_ratio: f64) -> HedgingPolicy {
+        HedgingPolicy { percentile: 0.5, min_delay: Duration::from_millis(10), max_hedge_ratio }
+    }
+
+    #[tokio::test]
+    async fn test_hedge_wins_when_primary_is_slow() {
+        let hedger = Hedger::new(policy(1.0));
+        let calls = AtomicUsize::new(0);
+        let result = hedger
+            .run(|| async {
+                let n = calls.fetch_add(1, Ordering::SeqCst);
+                let delay = if n == 0 { 5_000 } else { 1 };
+                tokio::time::sleep(Duration::from_millis(delay)).await;
+                Ok(n)
+            })
+            .await;
+        assert_eq!(result.unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_fast_requests_are_not_hedged() {
+        let hedger = Hedger::new(policy(1.0));
+        let calls = AtomicUsize::new(0);
+        let result = hedger.run(|| async { Ok(calls.fetch_add(1, Ordering::SeqCst)) }).await;
+        assert_eq!(result.unwrap(), 0);
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_budget_and_errors() {
+        // No budget: the slow primary is awaited.
+        let hedger = Hedger::new(policy(0.0));
+        let calls = AtomicUsize::new(0);
+        let request = || async {
+            let n = calls.fetch_add(1, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(30)).await;
+            Ok(n)
+        };
+        assert_eq!(hedger.run(request).await.unwrap(), 0);
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        // A failing primary yields to the hedge.
+        let hedger = Hedger::new(policy(1.0));
+        let calls = AtomicUsize::new(0);
+        let result = hedger
+            .run(|| async {
+                let n = calls.fetch_add(1, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis(20)).await;
+                if n == 0 { Err(Error::Api { status: 500, message: String::new() }) } else { Ok(n) }
+            })
+            .await;
+        assert_eq!(result.unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_records_each_successful_attempt() {
+        let latencies = |hedger: &Hedger| hedger.state.lock().unwrap().latencies.clone();
+
+        // The hedge's own latency, not the time since the primary started.
+        let hedger = Hedger::new(HedgingPolicy { min_delay: Duration::from_millis(100), ..policy(1.0) });
+        let calls = AtomicUsize::new(0);
+        hedger
+            .run(|| async {
+                let n = calls.fetch_add(1, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis(if n == 0 { 5_000 } else { 1 })).await;
+                Ok(n)
+            })
+            .await
+            .unwrap();
+        let recorded = latencies(&hedger);
+        assert_eq!(recorded.len(), 1);
+        assert!(recorded[0] < Duration::from_millis(100));
+
+        // Failures aren't recorded.
+        let hedger = Hedger::new(policy(0.0));
+        let failed: Result<()> = hedger.run(|| async { Err(Error::Api { status: 500, message: String::new() }) }).await;
+        assert!(failed.is_err());
+        assert!(latencies(&hedger).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_hedges_count_once_sent() {
+        let hedged = |hedger: &Hedger| hedger.state.lock().unwrap().hedged;
+        let hedger = Hedger::new(policy(1.0));
+
+        // A hedge still queued when the primary succeeds isn't counted.
+        let result = hedger
+            .run(|| async {
+                if is_hedge() {
+                    std::future::pending::<()>().await;
+                }
+                tokio::time::sleep(Duration::from_millis(30)).await;
+                Ok(())
+            })
+            .await;
+        assert!(result.is_ok());
+        assert_eq!(hedged(&hedger), 0);
+
+        let result = hedger
+            .run(|| async {
+                if is_hedge() {
+                    hedger.sent_hedge();
+                } else {
+                    tokio::time::sleep(Duration::from_millis(5_000)).await;
+                }
+                Ok(())
+            })
+            .await;
+        assert!(result.is_ok());
+        assert_eq!(hedged(&hedger), 1);
+    }
+
+    #[test]
+    fn test_delay_uses_percentile_after_enough_samples() {
+        let hedger = Hedger::new(policy(1.0));
+        assert_eq!(hedger.delay(), Duration::from_millis(10));
+        for ms in 1..=100 {
+            hedger.record(Duration::from_millis(ms));
+        }
+        assert_eq!(hedger.delay(), Duration::from_millis(50));
+    }
+}
@@ -0,0 +1,276 @@
+use std::collections::HashMap;
+
+use crate::rerank::relevance;
+use crate::{Id, MultiQueryParams, QueryParams, QueryResponse, RankBy, Row};
+
+/// How `fuse` combines the rankings of a hybrid search.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Fusion {
+    /// Reciprocal rank fusion: each component contributes `weight / (k + rank)`,
+    /// with ranks starting at 1. Scores are ignored, so distances and BM25
+    /// scores mix without normalization.
+    Rrf { k: f64 },
+    /// Scores are scaled to `[0, 1]` within each component, then summed by weight.
+    MinMax,
+    /// Scores are standardized within each component, then summed by weight.
+    ZScore,
+}
+
+impl Fusion {
+    /// Reciprocal rank fusion with the conventional `k = 60`.
+    pub fn rrf() -> Self {
+        Fusion::Rrf { k: 60.0 }
+    }
+}
+
+impl Default for Fusion {
+    fn default() -> Self {
+        Fusion::rrf()
+    }
+}
+
+/// One ranking in a hybrid search, e.g. a vector query or a BM25 query.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HybridComponent {
+    pub rank_by: RankBy,
+    pub weight: f64,
+}
+
+impl HybridComponent {
+    pub fn new(rank_by: RankBy, weight: f64) -> Self {
+        HybridComponent { rank_by, weight }
+    }
+}
+
+impl From<RankBy> for HybridComponent {
+    fn from(rank_by: RankBy) -> Self {
+        HybridComponent::new(rank_by, 1.0)
+    }
+}
+
+/// Parameters for `Namespace::hybrid_search`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct HybridSearchParams {
+    /// Shared by every component query: filters, attributes, consistency, and
+    /// `top_k` as the number of candidates fetched per component. Its
+    /// `rank_by` is ignored.
+    pub query: QueryParams,
+    pub components: Vec<HybridComponent>,
+    pub fusion: Fusion,
+    /// Number of fused hits to return; all candidates if unset.
+    pub top_k: Option<usize>,
+}
+
+impl HybridSearchParams {
+    /// The `multi_query` request running each component with the shared query.
+    pub fn to_multi_query(&self) -> MultiQueryParams {
+        MultiQueryParams::from_template(&self.query, self.components.iter().map(|c| c.rank_by.clone()))
+    }
+}
+
+/// A document's placement in one component's results.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ComponentScore {
+    /// 1-based rank within the component.
+    pub rank: usize,
+    /// The component's `$dist`, if returned.
+    pub dist: Option<f64>,
+    /// The weighted contribution to the fused score.
+    pub score: f64,
+}
+
+/// A document from a hybrid search, with its fused score and the score from
+/// each component that returned it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FusedHit {
+    pub id: Id,
+    pub score: f64,
+    /// The document's attributes, from the first component that returned it,
+    /// without `$dist`.
+    pub row: Row,
+    /// Indexed like `HybridSearchParams::components`; `None` where the
+    /// component didn't return the document.
+    pub components: Vec<Option<ComponentScore>>,
+}
+
+/// Merge the responses of a hybrid search, one per component in order, into
+/// hits sorted by fused score. Rows without an `id` are skipped.
+///
+/// Vector components rank by ascending `$dist` and everything else by
+/// descending `$dist`; rows without `$dist` are scored by rank. With `ZScore`,
+/// a document missing from a component gets that component's lowest score;
+/// with the other fusions it gets nothing.
+pub fn fuse(responses: &[QueryResponse], params: &HybridSearchParams) -> Vec<FusedHit> {
+    let mut hits: Vec<FusedHit> = Vec::new();
+    let mut index: HashMap<Id, usize> = HashMap::new();
+    // What each component contributes to documents it didn't return.
+    let mut fill = vec![0.0; params.components.len()];
+
+    for (c, (response, component)) in responses.iter().zip(&params.components).enumerate() {
+        let rows: Vec<(Id, &Row, Option<f64>)> = response
+            .rows
+            .iter()
+            .filter_map(|row| {
+                let id = Id::try_from(row.get("id")?.clone()).ok()?;
+                Some((id, row, row.get("$dist").and_then(|d| d.as_f64())))
+            })
+            .collect();
+
+        let raw: Vec<f64> = rows
+            .iter()
+            .enumerate()
+            .map(|(rank, (_, row, _))| relevance(Some(&component.rank_by), rank, row))
+            .collect();
+        let normalized = normalize(&raw, params.fusion);
+        if params.fusion == Fusion::ZScore {
+            fill[c] = component.weight * normalized.iter().copied().fold(0.0, f64::min);
+        }
+
+        for (rank, ((id, row, dist), norm)) in rows.into_iter().zip(normalized).enumerate() {
+            let score = component.weight
+                * match params.fusion {
+                    Fusion::Rrf { k } => 1.0 / (k + rank as f64 + 1.0),
+                    Fusion::MinMax | Fusion::ZScore => norm,
+                };
+            let i = *index.entry(id.clone()).or_insert_with(|| {
+                let mut row = row.clone();
+                row.remove("$dist");
+                hits.push(FusedHit { id, score: 0.0, row, components: vec![None; params.components.len()] });
+                hits.len() - 1
+            });
+            hits[i].components[c] = Some(ComponentScore { rank: rank + 1, dist, score });
+        }
+    }
+
+    for hit in &mut hits {
+        hit.score = hit
+            .components
+            .iter()
+            .zip(&fill)
+            .map(|(s, fill)| s.as_ref().map_or(*fill, |s| s.score))
+            .sum();
+    }
+
+    // Stable, so ties keep the order documents were first returned in.
+    hits.sort_by(|a, b| b.score.total_cmp(&a.score));
+    if let Some(top_k) = params.top_k {
+        hits.truncate(top_k);
+    }
+    hits
+}
+
+fn normalize(raw: &[f64], fusion: Fusion) -> Vec<f64> {
+    match fusion {
+        Fusion::Rrf { .. } => raw.to_vec(),
+        Fusion::MinMax => {
+            let min = raw.iter().copied().fold(f64::INFINITY, f64::min);
+            let max = raw.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+            raw.iter()
+                .map(|x| if max > min { (x - min) / (max - min) } else { 1.0 })
+                .collect()
+        }
+        Fusion::ZScore => {
+            let n = raw.len() as f64;
+            let mean = raw.iter().sum::<f64>() / n;
+            let std = (raw.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / n).sqrt();
+            raw.iter()
+                .map(|x| if std > 0.0 { (x - mean) / std } else { 0.0 })
+                .collect()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn response(rows: serde_json::Value) -> QueryResponse {
+        serde_json::from_value(json!({ "rows": rows })).unwrap()
+    }
+
+    fn params(fusion: Fusion) -> HybridSearchParams {
+        HybridSearchParams {
+            components: vec![
+                RankBy::vector("embedding", vec![0.1, 0.2]).into(),
+                RankBy::bm25("title", "query").into(),
+            ],
+            fusion,
+            ..Default::default()
+        }
+    }
+
+    fn responses() -> Vec<QueryResponse> {
+        vec![
+            response(json!([
+                {"id": 1, "$dist": 0.1, "title": "a"},
+                {"id": 2, "$dist": 0.2, "title": "b"},
+                {"id": 3, "$dist": 0.9, "title": "c"},
+            ])),
+            response(json!([
+                {"id": 3, "$dist": 12.0, "title": "c"},
+                {"id": 2, "$dist": 8.0, "title": "b"},
+                {"id": 4, "$dist": 1.0, "title": "d"},
+            ])),
+        ]
+    }
+
+    fn ids(hits: &[FusedHit]) -> Vec<Id> {
+        hits.iter().map(|h| h.id.clone()).collect()
+    }
+
+    #[test]
+    fn test_rrf() {
+        let hits = fuse(&responses(), &params(Fusion::rrf()));
+        assert_eq!(ids(&hits), vec![Id::from(3u64), 2u64.into(), 1u64.into(), 4u64.into()]);
+
+        let hit = &hits[1];
+        assert_eq!(hit.score, 1.0 / 62.0 + 1.0 / 62.0);
+        assert_eq!(hit.row, serde_json::from_value::<Row>(json!({"id": 2, "title": "b"})).unwrap());
+        assert_eq!(
+            hit.components,
+            vec![
+                Some(ComponentScore { rank: 2, dist: Some(0.2), score: 1.0 / 62.0 }),
+                Some(ComponentScore { rank: 2, dist: Some(8.0), score: 1.0 / 62.0 }),
+            ]
+        );
+        assert_eq!(hits[2].components[1], None);
+    }
+
+    #[test]
+    fn test_min_max() {
+        let mut p = params(Fusion::MinMax);
+        p.components[1].weight = 2.0;
+        p.top_k = Some(2);
+        let hits = fuse(&responses(), &p);
+        // 3: 0 + 2 * 1, 2: 0.875 + 2 * 7/11
+        assert_eq!(ids(&hits), vec![Id::from(2u64), 3u64.into()]);
+        assert_eq!(hits[1].score, 2.0);
+    }
+
+    #[test]
+    fn test_z_score_fills_missing_with_lowest() {
+        let hits = fuse(&responses(), &params(Fusion::ZScore));
+        assert_eq!(ids(&hits), vec![Id::from(2u64), 3u64.into(), 1u64.into(), 4u64.into()]);
+        // Component z-scores: vector [0.843, 0.562, -1.405] for 1, 2, 3 and
+        // BM25 [1.100, 0.220, -1.320] for 3, 2, 4. 1 is missing from BM25 and
+        // 4 from vector, so each gets that component's lowest.
+        let expected = [0.7819221594810465, -0.3050253547123911, -0.47689680476865504, -2.7247027525647205];
+        for (hit, expected) in hits.iter().zip(expected) {
+            assert!((hit.score - expected).abs() < 1e-9, "{:?}: {} != {}", hit.id, hit.score, expected);
+        }
+    }
+
+    #[test]
+    fn test_to_multi_query() {
+        let mut p = params(Fusion::rrf());
+        p.query.top_k = Some(50);
+        p.query.consistency = Some(crate::Consistency { level: crate::ConsistencyLevel::Eventual });
+        let multi = p.to_multi_query();
+        assert_eq!(multi.queries.len(), 2);
+        assert_eq!(multi.queries[1].rank_by, Some(RankBy::bm25("title", "query")));
+        assert_eq!(multi.queries[1].top_k, Some(50));
+        assert_eq!(multi.queries[0].consistency, None);
+        assert!(multi.consistency.is_some());
+    }
+}
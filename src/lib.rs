@@ -1,17 +1,45 @@
+mod attr;
+mod breaker;
+mod cache;
 mod client;
+#[cfg(any(feature = "gzip", feature = "zstd"))]
+mod compression;
 mod error;
 mod filter;
+mod hedge;
+mod hybrid;
+mod limits;
+mod lint;
 mod namespace;
 pub mod params;
+mod query_stream;
 mod rank_by;
+mod rate_limit;
+mod rerank;
+mod single_flight;
+mod streaming;
+mod throttle;
 pub mod responses;
 pub mod types;
 
+pub use attr::{Attr, AttrType, IntoAttrValue, Ordered};
+pub use breaker::{BreakerScope, CircuitBreakerConfig, CircuitState};
+pub use cache::QueryCacheConfig;
 pub use client::{Client, NamespacesParams};
+#[cfg(any(feature = "gzip", feature = "zstd"))]
+pub use compression::{Compression, RequestCompression};
 pub use error::{Error, Result};
-pub use filter::{ContainsAllTokensParams, Filter};
+pub use filter::{CompiledFilter, ContainsAllTokensParams, Filter};
+pub use hedge::HedgingPolicy;
+pub use hybrid::{ComponentScore, FusedHit, Fusion, HybridComponent, HybridSearchParams, fuse};
+pub use limits::ConcurrencyLimits;
+pub use lint::LintIssue;
 pub use namespace::Namespace;
 pub use params::*;
-pub use rank_by::{Bm25Params, Order, RankBy};
+pub use query_stream::QueryStream;
+pub use rank_by::{Bm25Params, Order, QueryVector, RankBy};
+pub use rate_limit::{Rate, RateLimits};
+pub use rerank::{AttributeBoost, ExactRescore, RecencyDecay, Reranker, ScoredRow, rerank, rerank_multi};
 pub use responses::*;
+pub use throttle::{AdaptiveWriteConfig, AdaptiveWriteReport};
 pub use types::*;
@@ -0,0 +1,183 @@
+use reqwest::Method;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+/// Caps on in-flight requests, enabled with `Client::with_concurrency_limits`.
+/// Requests over a limit wait in FIFO order rather than fail.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ConcurrencyLimits {
+    /// All requests made by the client.
+    pub global: Option<usize>,
+    /// Requests to any one namespace.
+    pub per_namespace: Option<usize>,
+    /// Overrides `per_namespace` for specific namespaces.
+    pub namespaces: HashMap<String, usize>,
+    /// `query` and `multi_query` requests across all namespaces.
+    pub queries: Option<usize>,
+    /// `write` and `delete_all` requests across all namespaces.
+    pub writes: Option<usize>,
+}
+
+/// The kind of operation a request performs, for per-operation limits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Operation {
+    Query,
+    Write,
+    Other,
+}
+
+/// The namespace a request targets and what it does, derived from the
+/// request path.
+pub(crate) fn classify<'a>(method: &Method, path: &'a str) -> (Option<&'a str>, Operation) {
+    let Some(rest) = path.strip_prefix("/v1/namespaces/").or_else(|| path.strip_prefix("/v2/namespaces/")) else {
+        return (None, Operation::Other);
+    };
+    let (namespace, suffix) = match rest.find(['/', '?']) {
+        Some(i) => rest.split_at(i),
+        None => (rest, ""),
+    };
+    let operation = match (method, suffix) {
+        (&Method::POST, "/query") => Operation::Query,
+        (&Method::POST | &Method::DELETE, "") if path.starts_with("/v2/") => Operation::Write,
+        _ => Operation::Other,
+    };
+    (Some(namespace), operation)
+}
+
+pub(crate) struct ConcurrencyLimiter {
+    limits: ConcurrencyLimits,
+    global: Option<Arc<Semaphore>>,
+    queries: Option<Arc<Semaphore>>,
+    writes: Option<Arc<Semaphore>>,
+    namespaces: Mutex<HashMap<String, Arc<Semaphore>>>,
+}
+
+/// Held for the duration of a request; dropping it frees the slots.
+pub(crate) struct Permits {
+    _permits: Vec<OwnedSemaphorePermit>,
+}
+
+impl ConcurrencyLimiter {
+    pub(crate) fn new(limits: ConcurrencyLimits) -> Self {
+        let semaphore = |limit: Option<usize>| limit.map(|n| Arc::new(Semaphore::new(n)));
+        ConcurrencyLimiter {
+            global: semaphore(limits.global),
+            queries: semaphore(limits.queries),
+            writes: semaphore(limits.writes),
+            namespaces: Mutex::new(HashMap::new()),
+            limits,
+        }
+    }
+
+    /// The namespace's semaphore. Semaphores referenced only by the map have
+    /// no permits out and no waiters, so they are dropped whenever a new
+    /// namespace is added, keeping the map to the namespaces in use.
+    fn namespace(&self, namespace: &str) -> Option<Arc<Semaphore>> {
+        let limit = self.limits.namespaces.get(namespace).copied().or(self.limits.per_namespace)?;
+        let mut namespaces = self.namespaces.lock().unwrap();
+        if let Some(semaphore) = namespaces.get(namespace) {
+            return Some(semaphore.clone());
+        }
+        namespaces.retain(|_, semaphore| Arc::strong_count(semaphore) > 1);
+        let semaphore = Arc::new(Semaphore::new(limit));
+        namespaces.insert(namespace.to_string(), semaphore.clone());
+        Some(semaphore)
+    }
+
+    /// Wait for a slot under every limit that applies. Slots are always taken
+    /// in the same order, so requests can't deadlock holding each other's
+    /// slots, and from the most specific limit to the least (namespace,
+    /// operation, global), so a request queued behind its own busy namespace
+    /// doesn't hold a shared slot that requests to other namespaces need.
+    pub(crate) async fn acquire(&self, namespace: Option<&str>, operation: Operation) -> Permits {
+        let by_operation = match operation {
+            Operation::Query => self.queries.clone(),
+            Operation::Write => self.writes.clone(),
+            Operation::Other => None,
+        };
+        let by_namespace = namespace.and_then(|ns| self.namespace(ns));
+
+        let mut permits = Vec::with_capacity(3);
+        for semaphore in [by_namespace, by_operation, self.global.clone()].into_iter().flatten() {
+            permits.push(semaphore.acquire_owned().await.expect("semaphores are never closed"));
+        }
+        Permits { _permits: permits }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::FutureExt;
+
+    #[test]
+    fn test_classify() {
+        assert_eq!(classify(&Method::POST, "/v2/namespaces/docs/query"), (Some("docs"), Operation::Query));
+        assert_eq!(classify(&Method::POST, "/v2/namespaces/docs"), (Some("docs"), Operation::Write));
+        assert_eq!(classify(&Method::DELETE, "/v2/namespaces/docs"), (Some("docs"), Operation::Write));
+        assert_eq!(classify(&Method::GET, "/v1/namespaces/docs/metadata"), (Some("docs"), Operation::Other));
+        assert_eq!(classify(&Method::GET, "/v1/namespaces?prefix=a"), (None, Operation::Other));
+    }
+
+    #[tokio::test]
+    async fn test_limits_queue_requests() {
+        let limiter = ConcurrencyLimiter::new(ConcurrencyLimits {
+            per_namespace: Some(1),
+            namespaces: HashMap::from([("bulk".to_string(), 2)]),
+            writes: Some(2),
+            ..Default::default()
+        });
+
+        let a = limiter.acquire(Some("docs"), Operation::Query).await;
+        // Same namespace waits; other namespaces don't.
+        assert!(limiter.acquire(Some("docs"), Operation::Query).now_or_never().is_none());
+        let _b = limiter.acquire(Some("other"), Operation::Query).await;
+        drop(a);
+        assert!(limiter.acquire(Some("docs"), Operation::Query).now_or_never().is_some());
+
+        // The override allows two, and the write limit caps writes overall.
+        let _w1 = limiter.acquire(Some("bulk"), Operation::Write).await;
+        let _w2 = limiter.acquire(Some("bulk"), Operation::Write).await;
+        assert!(limiter.acquire(Some("other2"), Operation::Write).now_or_never().is_none());
+        assert!(limiter.acquire(Some("other2"), Operation::Query).now_or_never().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_busy_namespace_does_not_block_others() {
+        let limiter = ConcurrencyLimiter::new(ConcurrencyLimits {
+            per_namespace: Some(1),
+            queries: Some(2),
+            ..Default::default()
+        });
+
+        let _x = limiter.acquire(Some("x"), Operation::Query).await;
+        // Queued behind its namespace without taking the second query slot.
+        let mut queued = Box::pin(limiter.acquire(Some("x"), Operation::Query));
+        assert!(queued.as_mut().now_or_never().is_none());
+        assert!(limiter.acquire(Some("y"), Operation::Query).now_or_never().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_idle_namespaces_are_dropped() {
+        let limiter = ConcurrencyLimiter::new(ConcurrencyLimits { per_namespace: Some(1), ..Default::default() });
+        let names = || {
+            let mut names: Vec<String> = limiter.namespaces.lock().unwrap().keys().cloned().collect();
+            names.sort();
+            names
+        };
+
+        let held = limiter.acquire(Some("a"), Operation::Query).await;
+        drop(limiter.acquire(Some("b"), Operation::Query).await);
+        assert_eq!(names(), vec!["a", "b"]);
+
+        // "b" is idle and goes when "c" arrives; "a" still holds its slot.
+        drop(limiter.acquire(Some("c"), Operation::Query).await);
+        assert_eq!(names(), vec!["a", "c"]);
+        assert!(limiter.acquire(Some("a"), Operation::Query).now_or_never().is_none());
+
+        drop(held);
+        drop(limiter.acquire(Some("d"), Operation::Query).await);
+        assert_eq!(names(), vec!["d"]);
+    }
+}
@@ -0,0 +1,317 @@
+use serde_json::Value;
+use std::fmt;
+
+use crate::{AttributeSchema, Filter, QueryParams, RankBy, SchemaResponse};
+
+/// A problem found by `QueryParams::lint` that the server would reject, or
+/// that makes the query match nothing.
+#[derive(Debug, Clone, PartialEq)]
+pub enum LintIssue {
+    /// The attribute isn't in the namespace schema.
+    UnknownAttribute { attr: String },
+    /// A filter on an attribute with `filterable: false`.
+    NotFilterable { attr: String },
+    /// `Bm25`, `ContainsAllTokens` or `ContainsTokenSequence` on an attribute
+    /// without full-text search.
+    NoFullTextSearch { attr: String },
+    /// `Regex` on an attribute without `regex: true`.
+    RegexNotEnabled { attr: String },
+    /// A filter value that doesn't fit the attribute's type.
+    TypeMismatch { attr: String, expected: String, value: Value },
+    /// A query vector whose length differs from the vector attribute's.
+    VectorDimensionMismatch { attr: String, expected: usize, actual: usize },
+    /// A `Sum` or `Max` with no subqueries.
+    EmptyCombinator { op: &'static str },
+}
+
+impl fmt::Display for LintIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LintIssue::UnknownAttribute { attr } => write!(f, "attribute '{}' is not in the schema", attr),
+            LintIssue::NotFilterable { attr } => write!(f, "attribute '{}' is not filterable", attr),
+            LintIssue::NoFullTextSearch { attr } => {
+                write!(f, "attribute '{}' does not have full-text search enabled", attr)
+            }
+            LintIssue::RegexNotEnabled { attr } => write!(f, "attribute '{}' does not have regex enabled", attr),
+            LintIssue::TypeMismatch { attr, expected, value } => {
+                write!(f, "attribute '{}' has type {}, but is compared to {}", attr, expected, value)
+            }
+            LintIssue::VectorDimensionMismatch { attr, expected, actual } => write!(
+                f,
+                "attribute '{}' has {} dimensions, but the query vector has {}",
+                attr, expected, actual
+            ),
+            LintIssue::EmptyCombinator { op } => write!(f, "{} has no subqueries", op),
+        }
+    }
+}
+
+impl SchemaResponse {
+    /// The schema of one attribute. A bare type string is accepted in place
+    /// of a schema object.
+    pub fn attribute(&self, name: &str) -> Option<AttributeSchema> {
+        match self.0.get(name)? {
+            Value::String(attr_type) => Some(AttributeSchema::new(attr_type.clone())),
+            other => serde_json::from_value(other.clone()).ok(),
+        }
+    }
+}
+
+impl QueryParams {
+    /// Check the query against a namespace schema, from `Namespace::schema()`
+    /// or a cached copy, and report problems the server would otherwise
+    /// answer with a 400.
+    ///
+    /// An empty result doesn't guarantee the query succeeds; the lint only
+    /// covers what can be checked without the data.
+    pub fn lint(&self, schema: &SchemaResponse) -> Vec<LintIssue> {
+        let mut linter = Linter { schema, issues: Vec::new() };
+        if let Some(filter) = &self.filters {
+            linter.filter(filter);
+        }
+        if let Some(rank_by) = &self.rank_by {
+            linter.rank_by(rank_by);
+        }
+        linter.issues
+    }
+}
+
+struct Linter<'a> {
+    schema: &'a SchemaResponse,
+    issues: Vec<LintIssue>,
+}
+
+impl Linter<'_> {
+    fn push(&mut self, issue: LintIssue) {
+        if !self.issues.contains(&issue) {
+            self.issues.push(issue);
+        }
+    }
+
+    fn attribute(&mut self, attr: &str) -> Option<AttributeSchema> {
+        let schema = self.schema.attribute(attr);
+        if schema.is_none() && attr != "id" {
+            self.push(LintIssue::UnknownAttribute { attr: attr.to_string() });
+        }
+        schema
+    }
+
+    fn filter(&mut self, filter: &Filter) {
+        match filter {
+            Filter::And(filters) | Filter::Or(filters) => filters.iter().for_each(|f| self.filter(f)),
+            Filter::Not(filter) => self.filter(filter),
+
+            Filter::ContainsAllTokens { attr, .. } | Filter::ContainsTokenSequence { attr, .. } => {
+                self.full_text_search(attr)
+            }
+
+            Filter::Eq { attr, value }
+            | Filter::NotEq { attr, value }
+            | Filter::Lt { attr, value }
+            | Filter::Lte { attr, value }
+            | Filter::Gt { attr, value }
+            | Filter::Gte { attr, value } => self.compare(attr, std::slice::from_ref(value), true),
+
+            Filter::AnyLt { attr, value }
+            | Filter::AnyLte { attr, value }
+            | Filter::AnyGt { attr, value }
+            | Filter::AnyGte { attr, value }
+            | Filter::Contains { attr, value }
+            | Filter::NotContains { attr, value } => self.compare(attr, std::slice::from_ref(value), false),
+
+            Filter::In { attr, values }
+            | Filter::NotIn { attr, values }
+            | Filter::ContainsAny { attr, values }
+            | Filter::NotContainsAny { attr, values } => self.compare(attr, values, false),
+
+            Filter::Glob { attr, pattern }
+            | Filter::NotGlob { attr, pattern }
+            | Filter::IGlob { attr, pattern }
+            | Filter::NotIGlob { attr, pattern } => self.compare(attr, &[Value::from(pattern.as_str())], false),
+
+            Filter::Regex { attr, pattern } => {
+                self.compare(attr, &[Value::from(pattern.as_str())], false);
+                if let Some(schema) = self.schema.attribute(attr)
+                    && schema.regex != Some(true)
+                {
+                    self.push(LintIssue::RegexNotEnabled { attr: attr.clone() });
+                }
+            }
+        }
+    }
+
+    /// Check that the attribute is filterable and that each value fits its
+    /// element type. With `whole`, an array value is compared element-wise
+    /// against an array attribute.
+    fn compare(&mut self, attr: &str, values: &[Value], whole: bool) {
+        let Some(schema) = self.attribute(attr) else {
+            return;
+        };
+        if schema.filterable == Some(false) {
+            self.push(LintIssue::NotFilterable { attr: attr.to_string() });
+        }
+        let Some(attr_type) = schema.attr_type.as_deref() else {
+            return;
+        };
+        let (is_array, element) = match attr_type.strip_prefix("[]") {
+            Some(element) => (true, element),
+            None => (false, attr_type),
+        };
+        for value in values {
+            let fits = match value {
+                Value::Array(items) if whole && is_array => items.iter().all(|v| fits_type(element, v)),
+                Value::Array(_) => false,
+                v => fits_type(element, v),
+            };
+            if !fits {
+                self.push(LintIssue::TypeMismatch {
+                    attr: attr.to_string(),
+                    expected: attr_type.to_string(),
+                    value: value.clone(),
+                });
+            }
+        }
+    }
+
+    fn full_text_search(&mut self, attr: &str) {
+        let Some(schema) = self.attribute(attr) else {
+            return;
+        };
+        let enabled = match &schema.full_text_search {
+            None | Some(Value::Null) | Some(Value::Bool(false)) => false,
+            Some(_) => true,
+        };
+        if !enabled {
+            self.push(LintIssue::NoFullTextSearch { attr: attr.to_string() });
+        }
+    }
+
+    fn rank_by(&mut self, rank_by: &RankBy) {
+        match rank_by {
+            RankBy::Vector { attr, query } | RankBy::VectorKnn { attr, query } => {
+                let Some(schema) = self.attribute(attr) else {
+                    return;
+                };
+                if let Some((expected, _)) = schema.vector_dims()
+                    && expected != query.len()
+                {
+                    self.push(LintIssue::VectorDimensionMismatch {
+                        attr: attr.clone(),
+                        expected,
+                        actual: query.len(),
+                    });
+                }
+            }
+            RankBy::Bm25 { attr, .. } => self.full_text_search(attr),
+            RankBy::Attribute { attr, .. } => {
+                self.attribute(attr);
+            }
+            RankBy::Sum(subqueries) | RankBy::Max(subqueries) => {
+                if subqueries.is_empty() {
+                    let op = if matches!(rank_by, RankBy::Sum(_)) { "Sum" } else { "Max" };
+                    self.push(LintIssue::EmptyCombinator { op });
+                }
+                subqueries.iter().for_each(|r| self.rank_by(r));
+            }
+            RankBy::Product { subquery, .. } => self.rank_by(subquery),
+        }
+    }
+}
+
+/// Whether a scalar JSON value fits a turbopuffer element type. Null fits
+/// everything, and unrecognized types are assumed to fit.
+fn fits_type(element: &str, value: &Value) -> bool {
+    match (element, value) {
+        (_, Value::Null) => true,
+        ("string" | "uuid" | "datetime", v) => v.is_string(),
+        ("int", v) => v.is_i64(),
+        ("uint", v) => v.is_u64(),
+        ("float", v) => v.is_number(),
+        ("bool", v) => v.is_boolean(),
+        _ => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn schema() -> SchemaResponse {
+        serde_json::from_value(json!({
+            "id": {"type": "uint"},
+            "age": {"type": "uint"},
+            "name": {"type": "string", "regex": true},
+            "email": {"type": "string"},
+            "tags": {"type": "[]string"},
+            "body": {"type": "string", "full_text_search": true, "filterable": false},
+            "embedding": {"type": "[3]f32", "ann": true},
+        }))
+        .unwrap()
+    }
+
+    fn lint(filters: Option<Filter>, rank_by: Option<RankBy>) -> Vec<LintIssue> {
+        QueryParams { filters, rank_by, ..Default::default() }.lint(&schema())
+    }
+
+    #[test]
+    fn test_clean_query() {
+        let issues = lint(
+            Some(Filter::and(vec![
+                Filter::gte("age", 18),
+                Filter::r#in("tags", vec!["rust".into()]),
+                Filter::eq("tags", json!(["a", "b"])),
+                Filter::regex("name", "^a"),
+                Filter::contains_all_tokens("body", "hello world"),
+            ])),
+            Some(RankBy::sum(vec![RankBy::bm25("body", "hello"), RankBy::vector("embedding", vec![0.1, 0.2, 0.3])])),
+        );
+        assert_eq!(issues, vec![]);
+    }
+
+    #[test]
+    fn test_filter_issues() {
+        let issues = lint(
+            Some(Filter::and(vec![
+                Filter::eq("age", "eighteen"),
+                Filter::gte("age", -1),
+                Filter::eq("body", "x"),
+                Filter::regex("email", ".*"),
+                Filter::contains_all_tokens("email", "x"),
+                Filter::not(Filter::eq("missing", 1)),
+            ])),
+            None,
+        );
+        assert_eq!(
+            issues,
+            vec![
+                LintIssue::TypeMismatch { attr: "age".into(), expected: "uint".into(), value: json!("eighteen") },
+                LintIssue::TypeMismatch { attr: "age".into(), expected: "uint".into(), value: json!(-1) },
+                LintIssue::NotFilterable { attr: "body".into() },
+                LintIssue::RegexNotEnabled { attr: "email".into() },
+                LintIssue::NoFullTextSearch { attr: "email".into() },
+                LintIssue::UnknownAttribute { attr: "missing".into() },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_rank_by_issues() {
+        let issues = lint(
+            None,
+            Some(RankBy::sum(vec![
+                RankBy::max(vec![]),
+                RankBy::bm25("name", "x"),
+                RankBy::vector("embedding", vec![0.1, 0.2]),
+            ])),
+        );
+        assert_eq!(
+            issues,
+            vec![
+                LintIssue::EmptyCombinator { op: "Max" },
+                LintIssue::NoFullTextSearch { attr: "name".into() },
+                LintIssue::VectorDimensionMismatch { attr: "embedding".into(), expected: 3, actual: 2 },
+            ]
+        );
+    }
+}
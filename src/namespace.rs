@@ -1,7 +1,12 @@
+use futures::{StreamExt, TryStreamExt, stream};
 use reqwest::Method;
 
+use crate::cache::canonical_json;
+use crate::client::RequestBody;
+use crate::streaming::write_body;
+use crate::throttle::write_adaptive;
 use crate::{
-    Client, Error, Result,
+    AdaptiveWriteConfig, AdaptiveWriteReport, CircuitState, Client, Error, FusedHit, QueryStream, HybridSearchParams, Id, QueryVector, RankBy, Reranker, Result, Row, ScoredRow, fuse, rerank, rerank_multi,
     params::{MultiQueryParams, QueryParams, WriteParams},
     responses::{
         DeleteAllResponse, HintCacheWarmResponse, MultiQueryResponse, NamespaceMetadata,
@@ -9,20 +14,48 @@ use crate::{
     },
 };
 
+/// The most queries the server accepts in one `multi_query` request.
+const MULTI_QUERY_LIMIT: usize = 16;
+
+/// How many `multi_query` requests `search_batch` keeps in flight.
+const SEARCH_BATCH_CONCURRENCY: usize = 8;
+
 pub struct Namespace<'a> {
     client: &'a Client,
     name: String,
+    schema: Option<SchemaResponse>,
 }
 
 impl<'a> Namespace<'a> {
     pub(crate) fn new(client: &'a Client, name: String) -> Self {
-        Self { client, name }
+        Self { client, name, schema: None }
+    }
+
+    /// Lint every query against `schema` before sending it, failing with
+    /// `Error::Lint` instead of making the request. See `QueryParams::lint`.
+    pub fn with_schema(mut self, schema: SchemaResponse) -> Self {
+        self.schema = Some(schema);
+        self
+    }
+
+    fn lint<'p>(&self, queries: impl IntoIterator<Item = &'p QueryParams>) -> Result<()> {
+        let Some(schema) = &self.schema else {
+            return Ok(());
+        };
+        let issues: Vec<_> = queries.into_iter().flat_map(|q| q.lint(schema)).collect();
+        if issues.is_empty() { Ok(()) } else { Err(Error::Lint(issues)) }
     }
 
     pub fn name(&self) -> &str {
         &self.name
     }
 
+    /// The state of the circuit breaker guarding this namespace, if the
+    /// client has one.
+    pub fn circuit_state(&self) -> Option<CircuitState> {
+        self.client.circuit_state_for(Some(&self.name))
+    }
+
     fn v1_path(&self, suffix: &str) -> String {
         format!("/v1/namespaces/{}{}", self.name, suffix)
     }
@@ -32,33 +65,228 @@ impl<'a> Namespace<'a> {
     }
 
     pub async fn write(&self, params: WriteParams) -> Result<WriteResponse> {
-        self.client
-            .request(Method::POST, &self.v2_path(""), Some(&params))
-            .await
+        self.write_ref(&params).await
+    }
+
+    async fn write_ref(&self, params: &WriteParams) -> Result<WriteResponse> {
+        let result = self.client
+            .request(Method::POST, &self.v2_path(""), Some(params))
+            .await;
+        self.written(result)
+    }
+
+    /// Upsert rows from a stream, serializing the request body as it is
+    /// sent so memory stays bounded however many rows there are. Rows can be
+    /// any serializable type; `template` supplies the other write params,
+    /// and its `upsert_rows` is ignored. Use `futures::stream::iter` for an
+    /// iterator. A row that fails to serialize fails the write with
+    /// `Error::Json`.
+    pub async fn upsert_rows_stream<S>(&self, rows: S, template: WriteParams) -> Result<WriteResponse>
+    where
+        S: futures::Stream + Send + 'static,
+        S::Item: serde::Serialize,
+    {
+        let (body, content_encoding, error) = write_body(
+            template,
+            rows,
+            #[cfg(any(feature = "gzip", feature = "zstd"))]
+            self.client.compression.as_ref(),
+        )
+        .await?;
+        let body = RequestBody { body: reqwest::Body::wrap_stream(body), content_encoding, error: Some(error) };
+        let result = self.client.execute(Method::POST, &self.v2_path(""), Some(body)).await;
+        self.written(result)
+    }
+
+    fn written(&self, result: Result<WriteResponse>) -> Result<WriteResponse> {
+        // Even a failed write may have been applied.
+        self.invalidate_cache();
+        if let Ok(response) = &result {
+            self.client.charge(&self.name, response.billed_bytes());
+        }
+        result
+    }
+
+    /// Upsert `rows` in batches whose size and concurrency adapt to server
+    /// backpressure, for bulk loads. Each batch is `template` with
+    /// `upsert_rows` set; batches may land out of order, so rows sharing an
+    /// ID should not be split across them. Fails with
+    /// `Error::InvalidArgument` if `max_batch_size` is zero or below
+    /// `min_batch_size`.
+    pub async fn upsert_rows_adaptive(
+        &self,
+        rows: Vec<Row>,
+        template: WriteParams,
+        config: AdaptiveWriteConfig,
+    ) -> Result<AdaptiveWriteReport> {
+        let write = |batch| {
+            let params = WriteParams { upsert_rows: Some(batch), ..template.clone() };
+            async move {
+                let result = self.write_ref(&params).await;
+                (result, params.upsert_rows.unwrap_or_default())
+            }
+        };
+        write_adaptive(rows, &config, write, || self.wait_for_index(config.poll_interval)).await
+    }
+
+    /// Poll `metadata()` until the index is no longer updating.
+    async fn wait_for_index(&self, poll_interval: std::time::Duration) -> Result<()> {
+        loop {
+            let metadata = self.metadata().await?;
+            if metadata.index.and_then(|i| i.status).as_deref() != Some("updating") {
+                return Ok(());
+            }
+            tokio::time::sleep(poll_interval).await;
+        }
+    }
+
+    fn invalidate_cache(&self) {
+        if let Some(cache) = &self.client.query_cache {
+            cache.invalidate(&self.name);
+        }
+    }
+
+    /// Delete documents by ID.
+    pub async fn delete_ids(&self, ids: impl IntoIterator<Item = impl Into<Id>>) -> Result<WriteResponse> {
+        self.write(WriteParams {
+            deletes: Some(ids.into_iter().map(Into::into).collect()),
+            ..Default::default()
+        })
+        .await
     }
 
     pub async fn query(&self, params: QueryParams) -> Result<QueryResponse> {
+        self.lint([&params])?;
+        let cache = self.client.query_cache.as_ref();
+        let key = cache.and_then(|c| c.key(&self.name, &params));
+        if let (Some(cache), Some(key)) = (cache, &key)
+            && let Some(response) = cache.get(key)
+        {
+            return Ok(response);
+        }
+
+        let path = self.v2_path("/query");
+        let request = || self.client.request(Method::POST, &path, Some(&params));
+        let send = || async {
+            let response: QueryResponse = self.client.hedged(request).await?;
+            self.client.charge(&self.name, response.billed_bytes());
+            Ok(response)
+        };
+        let response: QueryResponse = match (&self.client.single_flight, canonical_json(&params)) {
+            (Some(groups), Some(json)) => groups.queries.run(format!("{}\n{}", self.name, json), send).await?,
+            _ => send().await?,
+        };
+        if let (Some(cache), Some(key)) = (cache, key) {
+            cache.insert(key, response.clone());
+        }
+        Ok(response)
+    }
+
+    /// Run a query and decode its rows one at a time as the response body
+    /// arrives, as `Row` or any deserializable type. Aggregations and billing
+    /// are in `QueryStream::summary` once the stream is exhausted. Unlike
+    /// `query`, the response is never cached, coalesced or hedged.
+    pub async fn query_stream<T>(&self, params: QueryParams) -> Result<QueryStream<'a, T>> {
+        self.lint([&params])?;
+        let body = self.client.encode(&params)?;
+        let client = self.client;
+        let namespace = self.name.clone();
         self.client
-            .request(Method::POST, &self.v2_path("/query"), Some(&params))
+            .execute_with(Method::POST, &self.v2_path("/query"), Some(body), |resp, permits| async move {
+                Ok(QueryStream::new(client, namespace, resp, permits))
+            })
             .await
     }
 
     pub async fn multi_query(&self, params: MultiQueryParams) -> Result<MultiQueryResponse> {
-        self.client
-            .request(Method::POST, &self.v2_path("/query"), Some(&params))
-            .await
+        self.lint(&params.queries)?;
+        let path = self.v2_path("/query");
+        let response: MultiQueryResponse = self.client
+            .hedged(|| self.client.request(Method::POST, &path, Some(&params)))
+            .await?;
+        self.client.charge(&self.name, response.results.iter().map(QueryResponse::billed_bytes).sum());
+        Ok(response)
+    }
+
+    /// Run one query per vector, sharing the template's filters, attributes
+    /// and `top_k`, and return the responses in input order.
+    ///
+    /// The template's `rank_by` picks the vector attribute and ANN vs kNN; it
+    /// defaults to ANN on `vector`. Queries are split into `multi_query`
+    /// requests under the server's per-request limit, run concurrently.
+    pub async fn search_batch(&self, vectors: &[Vec<f32>], template: QueryParams) -> Result<Vec<QueryResponse>> {
+        let vectors = vectors.iter().map(|v| QueryVector::from(v.as_slice()));
+        self.run_search_batch(search_batch_requests(vectors, &template)?).await
+    }
+
+    /// `search_batch` with one query per row of a batch of embeddings.
+    #[cfg(feature = "ndarray")]
+    pub async fn search_batch_rows(
+        &self,
+        batch: ndarray::ArrayView2<'_, f32>,
+        template: QueryParams,
+    ) -> Result<Vec<QueryResponse>> {
+        self.run_search_batch(search_batch_requests(QueryVector::from_rows(batch), &template)?).await
+    }
+
+    async fn run_search_batch(&self, requests: Vec<MultiQueryParams>) -> Result<Vec<QueryResponse>> {
+        let responses: Vec<MultiQueryResponse> = stream::iter(requests)
+            .map(|params| self.multi_query(params))
+            .buffered(SEARCH_BATCH_CONCURRENCY)
+            .try_collect()
+            .await?;
+        Ok(search_batch_results(responses))
+    }
+
+    /// Query `top_k * overfetch` candidates, rerank them, and keep the best
+    /// `top_k` (10 if unset).
+    pub async fn query_reranked(
+        &self,
+        mut params: QueryParams,
+        reranker: &impl Reranker,
+        overfetch: u64,
+    ) -> Result<Vec<ScoredRow>> {
+        let top_k = overfetch_top_k(&mut params, overfetch);
+        let response = self.query(params.clone()).await?;
+        rerank(response, params.rank_by.as_ref(), reranker, top_k).await
+    }
+
+    /// `query_reranked` for each query of a `multi_query`: every query
+    /// fetches `top_k * overfetch` candidates, and each result set is
+    /// reranked and cut to its own `top_k` (10 if unset).
+    pub async fn multi_query_reranked(
+        &self,
+        mut params: MultiQueryParams,
+        reranker: &impl Reranker,
+        overfetch: u64,
+    ) -> Result<Vec<Vec<ScoredRow>>> {
+        let top_ks: Vec<usize> = params.queries.iter_mut().map(|q| overfetch_top_k(q, overfetch)).collect();
+        let response = self.multi_query(params.clone()).await?;
+        rerank_multi(response, &params.queries, reranker, &top_ks).await
+    }
+
+    /// Run each component of a hybrid search in one `multi_query` and fuse
+    /// the results client-side. See `fuse`.
+    pub async fn hybrid_search(&self, params: HybridSearchParams) -> Result<Vec<FusedHit>> {
+        let response = self.multi_query(params.to_multi_query()).await?;
+        Ok(fuse(&response.results, &params))
     }
 
     pub async fn delete_all(&self) -> Result<DeleteAllResponse> {
-        self.client
+        let result = self.client
             .request_no_body(Method::DELETE, &self.v2_path(""))
-            .await
+            .await;
+        self.invalidate_cache();
+        result
     }
 
     pub async fn metadata(&self) -> Result<NamespaceMetadata> {
-        self.client
-            .request_no_body(Method::GET, &self.v1_path("/metadata"))
-            .await
+        let path = self.v1_path("/metadata");
+        let send = || self.client.request_no_body(Method::GET, &path);
+        match &self.client.single_flight {
+            Some(groups) => groups.metadata.run(self.name.clone(), send).await,
+            None => send().await,
+        }
     }
 
     pub async fn schema(&self) -> Result<SchemaResponse> {
@@ -85,3 +313,141 @@ impl<'a> Namespace<'a> {
         }
     }
 }
+
+fn search_batch_requests(
+    vectors: impl IntoIterator<Item = QueryVector>,
+    template: &QueryParams,
+) -> Result<Vec<MultiQueryParams>> {
+    let (attr, knn) = match &template.rank_by {
+        None => ("vector", false),
+        Some(RankBy::Vector { attr, .. }) => (attr.as_str(), false),
+        Some(RankBy::VectorKnn { attr, .. }) => (attr.as_str(), true),
+        Some(other) => {
+            return Err(Error::InvalidArgument(format!(
+                "search_batch needs a vector rank_by template, got {:?}",
+                other
+            )));
+        }
+    };
+    let rank_by = |v: QueryVector| if knn { RankBy::vector_knn(attr, v) } else { RankBy::vector(attr, v) };
+    let mut vectors = vectors.into_iter().peekable();
+    let mut requests = Vec::new();
+    while vectors.peek().is_some() {
+        let chunk = vectors.by_ref().take(MULTI_QUERY_LIMIT).map(rank_by);
+        requests.push(MultiQueryParams::from_template(template, chunk));
+    }
+    Ok(requests)
+}
+
+/// One result per query vector, in input order.
+/// Raise the query's `top_k` (10 if unset) by the overfetch factor and
+/// return the original `top_k` to cut the reranked results to.
+fn overfetch_top_k(params: &mut QueryParams, overfetch: u64) -> usize {
+    let top_k = params.top_k.unwrap_or(10);
+    params.top_k = Some(top_k.saturating_mul(overfetch.max(1)));
+    top_k as usize
+}
+
+fn search_batch_results(responses: Vec<MultiQueryResponse>) -> Vec<QueryResponse> {
+    responses.into_iter().flat_map(|r| r.results).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Filter, Order};
+
+    #[test]
+    fn test_search_batch_requests() {
+        let vectors = (0..40).map(|i| QueryVector::from(vec![i as f32, 0.0]));
+        let template = QueryParams {
+            rank_by: Some(RankBy::vector_knn("embedding", vec![])),
+            filters: Some(Filter::eq("lang", "en")),
+            top_k: Some(5),
+            ..Default::default()
+        };
+        let requests = search_batch_requests(vectors, &template).unwrap();
+
+        assert_eq!(requests.iter().map(|r| r.queries.len()).collect::<Vec<_>>(), vec![16, 16, 8]);
+        let query = &requests[2].queries[7];
+        assert_eq!(query.rank_by, Some(RankBy::vector_knn("embedding", vec![39.0, 0.0])));
+        assert_eq!(query.filters, template.filters);
+        assert_eq!(query.top_k, Some(5));
+    }
+
+    #[test]
+    fn test_overfetch_top_k() {
+        let mut params = MultiQueryParams {
+            queries: vec![QueryParams { top_k: Some(5), ..Default::default() }, QueryParams::default()],
+            ..Default::default()
+        };
+        let top_ks: Vec<usize> = params.queries.iter_mut().map(|q| overfetch_top_k(q, 3)).collect();
+        assert_eq!(top_ks, vec![5, 10]);
+        assert_eq!(params.queries.iter().map(|q| q.top_k).collect::<Vec<_>>(), vec![Some(15), Some(30)]);
+        assert_eq!(overfetch_top_k(&mut QueryParams { top_k: Some(4), ..Default::default() }, 0), 4);
+    }
+
+    #[test]
+    fn test_search_batch_requests_rejects_non_vector_ranking() {
+        let template = QueryParams { rank_by: Some(RankBy::attribute("ts", Order::Desc)), ..Default::default() };
+        assert!(matches!(
+            search_batch_requests([QueryVector::from(vec![1.0])], &template),
+            Err(Error::InvalidArgument(_))
+        ));
+        assert!(search_batch_requests([], &QueryParams::default()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_search_batch_results() {
+        // Responses to 18 vectors: 16 queries in the first request, 2 in the second.
+        let response = |ids: std::ops::Range<u64>| -> MultiQueryResponse {
+            let results: Vec<_> = ids.map(|i| serde_json::json!({"rows": [{"id": i, "$dist": 0.5}]})).collect();
+            serde_json::from_value(serde_json::json!({ "results": results })).unwrap()
+        };
+        let results = search_batch_results(vec![response(0..16), response(16..18)]);
+        assert_eq!(results.len(), 18);
+        for (i, result) in results.iter().enumerate() {
+            assert_eq!(result.rows[0]["id"], i);
+        }
+    }
+
+    #[cfg(feature = "ndarray")]
+    #[test]
+    fn test_search_batch_requests_from_rows() {
+        let batch = ndarray::Array2::from_shape_fn((20, 2), |(i, j)| (i * 2 + j) as f32);
+        let requests = search_batch_requests(QueryVector::from_rows(batch.view()), &QueryParams::default()).unwrap();
+        assert_eq!(requests.iter().map(|r| r.queries.len()).collect::<Vec<_>>(), vec![16, 4]);
+        assert_eq!(requests[1].queries[3].rank_by, Some(RankBy::vector("vector", vec![38.0, 39.0])));
+    }
+
+    #[tokio::test]
+    async fn test_unserializable_row_is_not_a_transport_error() {
+        use crate::CircuitBreakerConfig;
+        use std::io::Read;
+
+        struct Unserializable;
+        impl serde::Serialize for Unserializable {
+            fn serialize<S: serde::Serializer>(&self, _: S) -> std::result::Result<S::Ok, S::Error> {
+                Err(serde::ser::Error::custom("unserializable"))
+            }
+        }
+
+        // A server that reads requests and never answers.
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        std::thread::spawn(move || {
+            for mut stream in listener.incoming().flatten() {
+                let _ = stream.read_to_end(&mut Vec::new());
+            }
+        });
+        let client = Client::with_base_url("key", url).with_circuit_breaker(CircuitBreakerConfig {
+            window_size: 1,
+            min_requests: 1,
+            ..Default::default()
+        });
+        let ns = client.namespace("docs");
+        let result = ns.upsert_rows_stream(stream::iter([Unserializable]), WriteParams::default()).await;
+        assert!(matches!(result, Err(Error::Json(_))), "{:?}", result);
+        assert_eq!(client.circuit_state(), Some(CircuitState::Closed));
+    }
+}
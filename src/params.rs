@@ -1,9 +1,9 @@
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
-use crate::{DistanceMetric, Filter, RankBy, VectorEncoding};
+use crate::{DistanceMetric, Filter, Id, RankBy, VectorEncoding};
 
-#[derive(Debug, Clone, Default, Serialize)]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub struct WriteParams {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub upsert_rows: Option<Vec<HashMap<String, serde_json::Value>>>,
@@ -18,7 +18,7 @@ pub struct WriteParams {
     pub patch_columns: Option<HashMap<String, Vec<serde_json::Value>>>,
 
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub deletes: Option<Vec<serde_json::Value>>,
+    pub deletes: Option<Vec<Id>>,
 
     #[serde(skip_serializing_if = "Option::is_none")]
     pub delete_by_filter: Option<Filter>,
@@ -57,13 +57,13 @@ pub struct WriteParams {
     pub copy_from_namespace: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct PatchByFilter {
     pub filters: Filter,
     pub patch: HashMap<String, serde_json::Value>,
 }
 
-#[derive(Debug, Clone, Default, Serialize)]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub struct QueryParams {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub rank_by: Option<RankBy>,
@@ -96,26 +96,26 @@ pub struct QueryParams {
     pub group_by: Option<Vec<String>>,
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(untagged)]
 pub enum IncludeAttributes {
     All(bool),
     List(Vec<String>),
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Consistency {
     pub level: ConsistencyLevel,
 }
 
-#[derive(Debug, Clone, Copy, Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum ConsistencyLevel {
     Strong,
     Eventual,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum AggregateBy {
     Count,
     Sum(String),
@@ -143,7 +143,22 @@ impl serde::Serialize for AggregateBy {
     }
 }
 
-#[derive(Debug, Clone, Default, Serialize)]
+impl<'de> serde::Deserialize<'de> for AggregateBy {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        use serde::de::Error;
+        let items = Vec::<String>::deserialize(deserializer)?;
+        match items.as_slice() {
+            [op] if op == "Count" => Ok(AggregateBy::Count),
+            [op, attr] if op == "Sum" => Ok(AggregateBy::Sum(attr.clone())),
+            _ => Err(D::Error::custom(format!("unrecognized aggregate_by {:?}", items))),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub struct MultiQueryParams {
     pub queries: Vec<QueryParams>,
 
@@ -153,3 +168,19 @@ pub struct MultiQueryParams {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub consistency: Option<Consistency>,
 }
+
+impl MultiQueryParams {
+    /// One query per ranking, sharing everything else with `template`.
+    /// `consistency` and `vector_encoding` apply to the whole request, so they
+    /// move from the template to the top level.
+    pub fn from_template(template: &QueryParams, rank_bys: impl IntoIterator<Item = RankBy>) -> Self {
+        let mut template = template.clone();
+        let consistency = template.consistency.take();
+        let vector_encoding = template.vector_encoding.take();
+        let queries = rank_bys
+            .into_iter()
+            .map(|rank_by| QueryParams { rank_by: Some(rank_by), ..template.clone() })
+            .collect();
+        MultiQueryParams { queries, vector_encoding, consistency }
+    }
+}
@@ -0,0 +1,236 @@
+use futures::Stream;
+use std::collections::VecDeque;
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use crate::limits::Permits;
+use crate::{Client, QueryResponse, Result, Row};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Rows {
+    Before,
+    Inside,
+    After,
+}
+
+/// Splits a query response body, fed in arbitrary chunks, into the raw JSON
+/// of each element of the top-level `rows` array and the rest of the body
+/// with `rows` emptied.
+struct RowSplitter {
+    section: Rows,
+    depth: usize,
+    in_string: bool,
+    escape: bool,
+    /// The last string seen directly inside the top-level object, which is
+    /// the key when followed by `:`.
+    key: Vec<u8>,
+    capturing_key: bool,
+    /// The last byte outside strings before the current one, ignoring
+    /// whitespace.
+    previous: u8,
+    rest: Vec<u8>,
+    row: Vec<u8>,
+}
+
+impl RowSplitter {
+    fn new() -> Self {
+        RowSplitter {
+            section: Rows::Before,
+            depth: 0,
+            in_string: false,
+            escape: false,
+            key: Vec::new(),
+            capturing_key: false,
+            previous: 0,
+            rest: Vec::new(),
+            row: Vec::new(),
+        }
+    }
+
+    fn feed(&mut self, chunk: &[u8], rows: &mut VecDeque<Vec<u8>>) {
+        for &b in chunk {
+            self.push(b, rows);
+        }
+    }
+
+    fn push(&mut self, b: u8, rows: &mut VecDeque<Vec<u8>>) {
+        let in_rows = self.section == Rows::Inside;
+        if self.in_string {
+            if self.escape {
+                self.escape = false;
+            } else if b == b'\\' {
+                self.escape = true;
+            } else if b == b'"' {
+                self.in_string = false;
+                self.capturing_key = false;
+            }
+            if self.capturing_key {
+                self.key.push(b);
+            }
+            if in_rows { self.row.push(b) } else { self.rest.push(b) }
+            return;
+        }
+
+        if in_rows && self.depth == 2 {
+            match b {
+                b',' | b']' => {
+                    if !self.row.is_empty() {
+                        rows.push_back(std::mem::take(&mut self.row));
+                    }
+                    if b == b']' {
+                        self.depth = 1;
+                        self.section = Rows::After;
+                        self.rest.push(b']');
+                    }
+                    return;
+                }
+                b' ' | b'\n' | b'\r' | b'\t' => return,
+                _ => {}
+            }
+        }
+
+        match b {
+            b'"' => {
+                self.in_string = true;
+                if !in_rows && self.depth == 1 {
+                    self.key.clear();
+                    self.capturing_key = true;
+                }
+            }
+            b'[' if self.section == Rows::Before
+                && self.depth == 1
+                && self.previous == b':'
+                && self.key == b"rows" =>
+            {
+                self.section = Rows::Inside;
+                self.depth += 1;
+                self.rest.push(b);
+                return;
+            }
+            b'{' | b'[' => self.depth += 1,
+            b'}' | b']' => self.depth = self.depth.saturating_sub(1),
+            _ => {}
+        }
+        if !b.is_ascii_whitespace() {
+            self.previous = b;
+        }
+        if in_rows { self.row.push(b) } else { self.rest.push(b) }
+    }
+
+    /// The body without its rows, once all of it has been fed.
+    fn finish(self) -> Vec<u8> {
+        self.rest
+    }
+}
+
+/// Rows of a query response, decoded one at a time as the body arrives.
+/// Returned by `Namespace::query_stream`.
+///
+/// Once the stream is exhausted, `summary` holds the rest of the response:
+/// aggregations, billing and performance, with `rows` empty.
+pub struct QueryStream<'a, T = Row> {
+    client: &'a Client,
+    namespace: String,
+    body: Pin<Box<dyn Stream<Item = reqwest::Result<bytes::Bytes>> + Send>>,
+    splitter: Option<RowSplitter>,
+    ready: VecDeque<Vec<u8>>,
+    summary: Option<QueryResponse>,
+    _permits: Option<Permits>,
+    _row: PhantomData<fn() -> T>,
+}
+
+impl<'a, T> QueryStream<'a, T> {
+    pub(crate) fn new(client: &'a Client, namespace: String, response: reqwest::Response, permits: Option<Permits>) -> Self {
+        QueryStream {
+            client,
+            namespace,
+            body: Box::pin(response.bytes_stream()),
+            splitter: Some(RowSplitter::new()),
+            ready: VecDeque::new(),
+            summary: None,
+            _permits: permits,
+            _row: PhantomData,
+        }
+    }
+
+    /// The response without its rows, available once every row has been
+    /// yielded.
+    pub fn summary(&self) -> Option<&QueryResponse> {
+        self.summary.as_ref()
+    }
+}
+
+impl<T: serde::de::DeserializeOwned> Stream for QueryStream<'_, T> {
+    type Item = Result<T>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        loop {
+            if let Some(row) = this.ready.pop_front() {
+                return Poll::Ready(Some(serde_json::from_slice(&row).map_err(Into::into)));
+            }
+            let Some(splitter) = this.splitter.as_mut() else {
+                return Poll::Ready(None);
+            };
+            match this.body.as_mut().poll_next(cx) {
+                Poll::Ready(Some(Ok(chunk))) => splitter.feed(&chunk, &mut this.ready),
+                Poll::Ready(Some(Err(e))) => {
+                    this.splitter = None;
+                    return Poll::Ready(Some(Err(e.into())));
+                }
+                Poll::Ready(None) => {
+                    let rest = this.splitter.take().map(RowSplitter::finish).unwrap_or_default();
+                    match serde_json::from_slice::<QueryResponse>(&rest) {
+                        Ok(summary) => {
+                            this.client.charge(&this.namespace, summary.billed_bytes());
+                            this.summary = Some(summary);
+                        }
+                        Err(e) => return Poll::Ready(Some(Err(e.into()))),
+                    }
+                }
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn split(body: &str, chunk_size: usize) -> (Vec<String>, String) {
+        let mut splitter = RowSplitter::new();
+        let mut rows = VecDeque::new();
+        for chunk in body.as_bytes().chunks(chunk_size) {
+            splitter.feed(chunk, &mut rows);
+        }
+        let rows = rows.into_iter().map(|r| String::from_utf8(r).unwrap()).collect();
+        (rows, String::from_utf8(splitter.finish()).unwrap())
+    }
+
+    #[test]
+    fn test_splits_rows_from_rest() {
+        let body = r#"{"aggregations": {"rows": [1]}, "rows": [ {"id": 1, "tags": ["a]", "b\","]},
+            {"id": "x", "nested": {"rows": [[]]}} ], "billing": {"billable_logical_bytes_queried": 5, "billable_logical_bytes_returned": 7}}"#;
+        for chunk_size in [1, 3, 7, body.len()] {
+            let (rows, rest) = split(body, chunk_size);
+            assert_eq!(rows, [r#"{"id": 1, "tags": ["a]", "b\","]}"#, r#"{"id": "x", "nested": {"rows": [[]]}}"#]);
+            let rest: QueryResponse = serde_json::from_str(&rest).unwrap();
+            assert!(rest.rows.is_empty());
+            assert_eq!(rest.billing.unwrap().billable_logical_bytes_returned, 7);
+            assert!(rest.aggregations.unwrap().contains_key("rows"));
+        }
+    }
+
+    #[test]
+    fn test_no_rows() {
+        let (rows, rest) = split(r#"{"rows": [], "aggregations": {"count": 3}}"#, 4);
+        assert!(rows.is_empty());
+        assert_eq!(rest, r#"{"rows": [], "aggregations": {"count": 3}}"#);
+
+        let (rows, rest) = split(r#"{"aggregations": {"count": 3}}"#, 4);
+        assert!(rows.is_empty());
+        assert_eq!(rest, r#"{"aggregations": {"count": 3}}"#);
+    }
+}
@@ -1,5 +1,9 @@
+use serde::de::{self, Deserializer};
 use serde::ser::{SerializeSeq, Serializer};
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::Vector;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
@@ -14,12 +18,156 @@ pub struct Bm25Params {
     pub last_as_prefix: Option<bool>,
 }
 
+/// The query vector of a `RankBy::Vector` or `RankBy::VectorKnn`.
+///
+/// Always sent as a JSON float array; f16 values are widened to f32 losslessly.
+/// Since both variants look the same on the wire, deserializing always gives
+/// `F32`, and equality compares the widened values: an `F16` query equals the
+/// `F32` query with the same values.
+#[derive(Debug, Clone)]
+pub enum QueryVector {
+    F32(Vec<f32>),
+    #[cfg(feature = "half")]
+    F16(Vec<half::f16>),
+}
+
+impl QueryVector {
+    pub fn len(&self) -> usize {
+        match self {
+            QueryVector::F32(v) => v.len(),
+            #[cfg(feature = "half")]
+            QueryVector::F16(v) => v.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn to_f32_vec(&self) -> Vec<f32> {
+        match self {
+            QueryVector::F32(v) => v.clone(),
+            #[cfg(feature = "half")]
+            QueryVector::F16(v) => v.iter().map(|x| x.to_f32()).collect(),
+        }
+    }
+}
+
+impl PartialEq for QueryVector {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (QueryVector::F32(a), QueryVector::F32(b)) => a == b,
+            #[cfg(feature = "half")]
+            (QueryVector::F16(a), QueryVector::F16(b)) => a == b,
+            #[cfg(feature = "half")]
+            _ => self.to_f32_vec() == other.to_f32_vec(),
+        }
+    }
+}
+
+impl From<Vec<f32>> for QueryVector {
+    fn from(v: Vec<f32>) -> Self {
+        QueryVector::F32(v)
+    }
+}
+
+impl From<&[f32]> for QueryVector {
+    fn from(v: &[f32]) -> Self {
+        QueryVector::F32(v.to_vec())
+    }
+}
+
+impl From<Vector> for QueryVector {
+    fn from(v: Vector) -> Self {
+        QueryVector::F32(v.into_vec())
+    }
+}
+
+#[cfg(feature = "half")]
+impl From<crate::F16Vector> for QueryVector {
+    fn from(v: crate::F16Vector) -> Self {
+        QueryVector::F16(v.into_vec())
+    }
+}
+
+#[cfg(feature = "ndarray")]
+mod ndarray_impls {
+    use super::QueryVector;
+    use ndarray::{Array1, ArrayView1, ArrayView2};
+
+    impl QueryVector {
+        /// One query vector per row of a batch of embeddings, each row copied.
+        pub fn from_rows(batch: ArrayView2<'_, f32>) -> Vec<QueryVector> {
+            batch.rows().into_iter().map(QueryVector::from).collect()
+        }
+    }
+
+    impl From<Array1<f32>> for QueryVector {
+        fn from(v: Array1<f32>) -> Self {
+            QueryVector::F32(crate::types::array_into_vec(v))
+        }
+    }
+
+    impl From<ArrayView1<'_, f32>> for QueryVector {
+        fn from(v: ArrayView1<'_, f32>) -> Self {
+            QueryVector::F32(v.to_vec())
+        }
+    }
+}
+
+#[cfg(feature = "nalgebra")]
+mod nalgebra_impls {
+    use super::QueryVector;
+    use nalgebra::{DVector, DVectorView};
+
+    impl From<DVector<f32>> for QueryVector {
+        fn from(v: DVector<f32>) -> Self {
+            QueryVector::F32(v.data.into())
+        }
+    }
+
+    impl From<DVectorView<'_, f32>> for QueryVector {
+        fn from(v: DVectorView<'_, f32>) -> Self {
+            QueryVector::F32(v.iter().copied().collect())
+        }
+    }
+}
+
+impl Serialize for QueryVector {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self {
+            QueryVector::F32(v) => v.serialize(serializer),
+            #[cfg(feature = "half")]
+            QueryVector::F16(v) => {
+                let mut seq = serializer.serialize_seq(Some(v.len()))?;
+                for x in v {
+                    seq.serialize_element(&x.to_f32())?;
+                }
+                seq.end()
+            }
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for QueryVector {
+    /// Accepts a float array or a base64 string of packed little-endian f32s.
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Vector::deserialize(deserializer).map(QueryVector::from)
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum RankBy {
     // Vector search: ["attr", "ANN", [vector]]
-    Vector { attr: String, query: Vec<f32> },
+    Vector { attr: String, query: QueryVector },
     // Exact kNN: ["attr", "kNN", [vector]]
-    VectorKnn { attr: String, query: Vec<f32> },
+    VectorKnn { attr: String, query: QueryVector },
     // BM25 text search: ["attr", "BM25", "query"]
     Bm25 { attr: String, query: String, params: Option<Bm25Params> },
     // Attribute ordering: ["attr", "asc"|"desc"]
@@ -31,12 +179,12 @@ pub enum RankBy {
 }
 
 impl RankBy {
-    pub fn vector(attr: impl Into<String>, query: Vec<f32>) -> Self {
-        RankBy::Vector { attr: attr.into(), query }
+    pub fn vector(attr: impl Into<String>, query: impl Into<QueryVector>) -> Self {
+        RankBy::Vector { attr: attr.into(), query: query.into() }
     }
 
-    pub fn vector_knn(attr: impl Into<String>, query: Vec<f32>) -> Self {
-        RankBy::VectorKnn { attr: attr.into(), query }
+    pub fn vector_knn(attr: impl Into<String>, query: impl Into<QueryVector>) -> Self {
+        RankBy::VectorKnn { attr: attr.into(), query: query.into() }
     }
 
     pub fn bm25(attr: impl Into<String>, query: impl Into<String>) -> Self {
@@ -70,6 +218,16 @@ impl RankBy {
     pub fn product(weight: f64, subquery: RankBy) -> Self {
         RankBy::Product { weight, subquery: Box::new(subquery) }
     }
+
+    /// Whether results come back by ascending `$dist` (a vector distance)
+    /// rather than descending score.
+    pub(crate) fn ranks_by_distance(&self) -> bool {
+        match self {
+            RankBy::Vector { .. } | RankBy::VectorKnn { .. } => true,
+            RankBy::Product { subquery, .. } => subquery.ranks_by_distance(),
+            _ => false,
+        }
+    }
 }
 
 impl Serialize for RankBy {
@@ -137,6 +295,82 @@ impl Serialize for RankBy {
     }
 }
 
+impl<'de> Deserialize<'de> for RankBy {
+    /// Accepts the tuple wire format produced by `Serialize`.
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let value = Value::deserialize(deserializer)?;
+        rank_by_from_value(value).map_err(de::Error::custom)
+    }
+}
+
+fn rank_by_from_value(value: Value) -> Result<RankBy, String> {
+    let Value::Array(mut items) = value else {
+        return Err(format!("expected rank_by array, got {}", value));
+    };
+
+    match items.as_mut_slice() {
+        // Combinators: ["Sum", [subqueries]], ["Max", [subqueries]]
+        [Value::String(op), Value::Array(children)] if op == "Sum" || op == "Max" => {
+            let subqueries = std::mem::take(children)
+                .into_iter()
+                .map(rank_by_from_value)
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(if op == "Sum" { RankBy::Sum(subqueries) } else { RankBy::Max(subqueries) })
+        }
+        // ["Product", weight, subquery]
+        [Value::String(op), Value::Number(weight), subquery] if op == "Product" => {
+            let weight = weight.as_f64().ok_or("invalid Product weight")?;
+            let subquery = rank_by_from_value(subquery.take())?;
+            Ok(RankBy::Product { weight, subquery: Box::new(subquery) })
+        }
+        // Attribute ordering: ["attr", "asc"|"desc"]
+        [Value::String(attr), Value::String(order)] => {
+            let order = match order.as_str() {
+                "asc" => Order::Asc,
+                "desc" => Order::Desc,
+                other => return Err(format!("unknown order {:?}", other)),
+            };
+            Ok(RankBy::Attribute { attr: std::mem::take(attr), order })
+        }
+        // ["attr", "ANN"|"kNN", vector], ["attr", "BM25", "query", params?]
+        [Value::String(attr), Value::String(op), arg, params @ ..] if params.len() <= 1 => {
+            let attr = std::mem::take(attr);
+            let op = op.as_str();
+            let arg = arg.take();
+            let params = params.first_mut().map(Value::take);
+            if params.is_some() && op != "BM25" {
+                return Err(format!("unexpected parameters for {}", op));
+            }
+            match op {
+                "ANN" | "kNN" => {
+                    let query: QueryVector = serde_json::from_value(arg)
+                        .map_err(|e| format!("invalid {} query vector: {}", op, e))?;
+                    Ok(if op == "ANN" {
+                        RankBy::Vector { attr, query }
+                    } else {
+                        RankBy::VectorKnn { attr, query }
+                    })
+                }
+                "BM25" => {
+                    let Value::String(query) = arg else {
+                        return Err(format!("expected BM25 query string, got {}", arg));
+                    };
+                    let params = params
+                        .map(serde_json::from_value)
+                        .transpose()
+                        .map_err(|e| format!("invalid BM25 params: {}", e))?;
+                    Ok(RankBy::Bm25 { attr, query, params })
+                }
+                other => Err(format!("unknown rank_by operator {:?}", other)),
+            }
+        }
+        _ => Err(format!("unrecognized rank_by {}", Value::Array(items))),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -232,6 +466,54 @@ mod tests {
         );
     }
 
+    #[cfg(feature = "half")]
+    #[test]
+    fn test_f16_vector_serialization() {
+        let r = RankBy::vector("vec", crate::F16Vector::from_f32_slice(&[0.5, -1.0]));
+        let json = serde_json::to_string(&r).unwrap();
+        assert_eq!(json, r#"["vec","ANN",[0.5,-1.0]]"#);
+        assert_eq!(serde_json::from_str::<RankBy>(&json).unwrap(), r);
+    }
+
+    #[cfg(feature = "ndarray")]
+    #[test]
+    fn test_ndarray_vector_serialization() {
+        let batch = ndarray::array![[0.5, 0.25], [1.0, 2.0]];
+        let r = RankBy::vector("vec", batch.row(1));
+        assert_eq!(serde_json::to_string(&r).unwrap(), r#"["vec","ANN",[1.0,2.0]]"#);
+        assert_eq!(QueryVector::from_rows(batch.view()).len(), 2);
+    }
+
+    #[test]
+    fn test_deserialization() {
+        let r: RankBy = serde_json::from_str(
+            r#"["Sum",[["Product",2.0,["title","BM25","fox",{"last_as_prefix":true}]],["vec","kNN",[0.5]],["ts","desc"]]]"#,
+        )
+        .unwrap();
+        assert_eq!(
+            r,
+            RankBy::sum(vec![
+                RankBy::product(
+                    2.0,
+                    RankBy::bm25_with_params("title", "fox", Bm25Params { last_as_prefix: Some(true) }),
+                ),
+                RankBy::vector_knn("vec", vec![0.5]),
+                RankBy::desc("ts"),
+            ])
+        );
+
+        let r: RankBy = serde_json::from_str(r#"["vec","ANN","AAAAPw=="]"#).unwrap();
+        assert_eq!(r, RankBy::vector("vec", vec![0.5]));
+    }
+
+    #[test]
+    fn test_deserialization_errors() {
+        assert!(serde_json::from_str::<RankBy>(r#"["a","sideways"]"#).is_err());
+        assert!(serde_json::from_str::<RankBy>(r#"["a","ANN","b",{}]"#).is_err());
+        assert!(serde_json::from_str::<RankBy>(r#"["a","BM25",1]"#).is_err());
+        assert!(serde_json::from_str::<RankBy>(r#"["Max"]"#).is_err());
+    }
+
     #[test]
     fn test_empty_vector() {
         let r = RankBy::vector("vec", vec![]);
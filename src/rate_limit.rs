@@ -0,0 +1,284 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::{Error, Result};
+
+/// A sustained rate; either limit may be unset. Up to one second's worth,
+/// and at least one request, can be spent in a burst. Rates must be positive.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Rate {
+    pub requests_per_sec: Option<f64>,
+    /// Billable logical bytes written, queried and returned, as reported in
+    /// each response's `billing`.
+    pub bytes_per_sec: Option<f64>,
+}
+
+/// Rate limits enabled with `Client::with_rate_limits`. Requests over a
+/// limit wait rather than fail.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RateLimits {
+    /// All requests made by the client.
+    pub global: Option<Rate>,
+    /// Requests to any one namespace.
+    pub per_namespace: Option<Rate>,
+    /// Overrides `per_namespace` for specific namespaces.
+    pub namespaces: HashMap<String, Rate>,
+}
+
+impl Rate {
+    fn validate(&self, scope: &str) -> Result<()> {
+        for (name, value) in [("requests_per_sec", self.requests_per_sec), ("bytes_per_sec", self.bytes_per_sec)] {
+            if let Some(value) = value
+                && (value <= 0.0 || value.is_nan())
+            {
+                return Err(Error::InvalidArgument(format!("{} {} must be positive, got {}", scope, name, value)));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+struct TokenBucket {
+    rate: f64,
+    capacity: f64,
+    tokens: f64,
+    refilled: Instant,
+}
+
+impl TokenBucket {
+    /// Holds at least one token, so rates under one per second still let a
+    /// request through.
+    fn new(rate: f64) -> Self {
+        let capacity = rate.max(1.0);
+        TokenBucket { rate, capacity, tokens: capacity, refilled: Instant::now() }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.refilled).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.capacity);
+        self.refilled = now;
+    }
+
+    /// How long until the bucket holds `tokens`.
+    fn wait_for(&self, tokens: f64) -> Duration {
+        if self.tokens >= tokens {
+            return Duration::ZERO;
+        }
+        Duration::from_secs_f64((tokens - self.tokens) / self.rate)
+    }
+}
+
+#[derive(Debug, Default)]
+struct Buckets {
+    requests: Option<TokenBucket>,
+    bytes: Option<TokenBucket>,
+}
+
+impl Buckets {
+    fn new(rate: Rate) -> Self {
+        Buckets { requests: rate.requests_per_sec.map(TokenBucket::new), bytes: rate.bytes_per_sec.map(TokenBucket::new) }
+    }
+
+    fn all(&mut self) -> impl Iterator<Item = &mut TokenBucket> {
+        self.requests.iter_mut().chain(self.bytes.iter_mut())
+    }
+
+    /// Whether every bucket has refilled to capacity, so the buckets are
+    /// interchangeable with fresh ones.
+    fn full(&mut self, now: Instant) -> bool {
+        self.all().all(|bucket| {
+            bucket.refill(now);
+            bucket.tokens >= bucket.capacity
+        })
+    }
+
+    /// How long until a request may be sent: one request token, and byte
+    /// spending not in debt.
+    fn wait(&self) -> Duration {
+        let requests = self.requests.as_ref().map_or(Duration::ZERO, |b| b.wait_for(1.0));
+        let bytes = self.bytes.as_ref().map_or(Duration::ZERO, |b| b.wait_for(0.0));
+        requests.max(bytes)
+    }
+}
+
+#[derive(Debug, Default)]
+struct RateState {
+    global: Buckets,
+    namespaces: HashMap<String, Buckets>,
+}
+
+pub(crate) struct RateLimiter {
+    limits: RateLimits,
+    state: Mutex<RateState>,
+}
+
+impl RateLimiter {
+    /// Fails with `Error::InvalidArgument` if any rate is zero, negative or
+    /// NaN.
+    pub(crate) fn new(limits: RateLimits) -> Result<Self> {
+        if let Some(rate) = &limits.global {
+            rate.validate("global")?;
+        }
+        if let Some(rate) = &limits.per_namespace {
+            rate.validate("per_namespace")?;
+        }
+        for (namespace, rate) in &limits.namespaces {
+            rate.validate(namespace)?;
+        }
+        let global = limits.global.map(Buckets::new).unwrap_or_default();
+        Ok(RateLimiter { limits, state: Mutex::new(RateState { global, namespaces: HashMap::new() }) })
+    }
+
+    /// The buckets that apply to a request, refilled to now.
+    fn buckets<'s>(&self, state: &'s mut RateState, namespace: Option<&str>) -> Vec<&'s mut Buckets> {
+        let now = Instant::now();
+        let mut buckets = vec![&mut state.global];
+        let rate = namespace.and_then(|ns| self.limits.namespaces.get(ns).copied().or(self.limits.per_namespace));
+        if let (Some(ns), Some(rate)) = (namespace, rate) {
+            // Full, debt-free buckets are as good as new ones, so drop them
+            // whenever a namespace is added to keep the map bounded.
+            if !state.namespaces.contains_key(ns) {
+                state.namespaces.retain(|_, buckets| !buckets.full(now));
+            }
+            buckets.push(state.namespaces.entry(ns.to_string()).or_insert_with(|| Buckets::new(rate)));
+        }
+        for bucket in buckets.iter_mut().flat_map(|b| b.all()) {
+            bucket.refill(now);
+        }
+        buckets
+    }
+
+    /// Wait until every applicable limit allows another request, then take
+    /// a request token from each.
+    pub(crate) async fn acquire(&self, namespace: Option<&str>) {
+        loop {
+            let wait = {
+                let mut state = self.state.lock().unwrap();
+                let mut buckets = self.buckets(&mut state, namespace);
+                let wait = buckets.iter().map(|b| b.wait()).max().unwrap_or_default();
+                if wait.is_zero() {
+                    for bucket in buckets.iter_mut().filter_map(|b| b.requests.as_mut()) {
+                        bucket.tokens -= 1.0;
+                    }
+                }
+                wait
+            };
+            if wait.is_zero() {
+                return;
+            }
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    /// Spend billed bytes after a response. Byte buckets may go into debt,
+    /// holding back later requests until it is repaid.
+    pub(crate) fn charge(&self, namespace: Option<&str>, bytes: u64) {
+        let mut state = self.state.lock().unwrap();
+        for bucket in self.buckets(&mut state, namespace).into_iter().filter_map(|b| b.bytes.as_mut()) {
+            bucket.tokens -= bytes as f64;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::FutureExt;
+
+    fn requests(n: f64) -> Rate {
+        Rate { requests_per_sec: Some(n), bytes_per_sec: None }
+    }
+
+    #[tokio::test]
+    async fn test_request_rate() {
+        let limiter = RateLimiter::new(RateLimits {
+            per_namespace: Some(requests(2.0)),
+            namespaces: HashMap::from([("bulk".to_string(), requests(1.0))]),
+            ..Default::default()
+        })
+        .unwrap();
+        limiter.acquire(Some("docs")).await;
+        limiter.acquire(Some("docs")).await;
+        assert!(limiter.acquire(Some("docs")).now_or_never().is_none());
+        assert!(limiter.acquire(Some("other")).now_or_never().is_some());
+        assert!(limiter.acquire(None).now_or_never().is_some());
+
+        limiter.acquire(Some("bulk")).await;
+        let started = Instant::now();
+        limiter.acquire(Some("bulk")).await;
+        assert!(started.elapsed() >= Duration::from_millis(900));
+    }
+
+    #[tokio::test]
+    async fn test_byte_debt_holds_back_requests() {
+        let limiter = RateLimiter::new(RateLimits {
+            global: Some(Rate { requests_per_sec: None, bytes_per_sec: Some(1000.0) }),
+            ..Default::default()
+        })
+        .unwrap();
+        limiter.acquire(Some("docs")).await;
+        limiter.charge(Some("docs"), 1100);
+        assert!(limiter.acquire(Some("other")).now_or_never().is_none());
+
+        let started = Instant::now();
+        limiter.acquire(Some("other")).await;
+        assert!(started.elapsed() >= Duration::from_millis(50));
+    }
+
+    #[tokio::test]
+    async fn test_fractional_rate() {
+        let limiter = RateLimiter::new(RateLimits { global: Some(requests(4.0 / 3.0)), ..Default::default() }).unwrap();
+        limiter.acquire(None).await;
+        assert!(limiter.acquire(None).now_or_never().is_none());
+
+        let limiter = RateLimiter::new(RateLimits { global: Some(requests(2.5)), ..Default::default() }).unwrap();
+        limiter.acquire(None).await;
+        limiter.acquire(None).await;
+        let started = Instant::now();
+        limiter.acquire(None).await;
+        assert!(started.elapsed() >= Duration::from_millis(150));
+
+        let limiter = RateLimiter::new(RateLimits { global: Some(requests(0.5)), ..Default::default() }).unwrap();
+        limiter.acquire(None).await;
+        assert!(tokio::time::timeout(Duration::from_millis(100), limiter.acquire(None)).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_full_namespace_buckets_are_dropped() {
+        let limiter = RateLimiter::new(RateLimits {
+            per_namespace: Some(requests(1.0)),
+            namespaces: HashMap::from([("fast".to_string(), requests(1000.0))]),
+            ..Default::default()
+        })
+        .unwrap();
+        let names = || {
+            let mut names: Vec<String> = limiter.state.lock().unwrap().namespaces.keys().cloned().collect();
+            names.sort();
+            names
+        };
+
+        limiter.acquire(Some("fast")).await;
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        limiter.acquire(Some("a")).await;
+        assert_eq!(names(), vec!["a"]);
+
+        // "a" is still refilling, so it stays.
+        limiter.acquire(Some("b")).await;
+        assert_eq!(names(), vec!["a", "b"]);
+        assert!(limiter.acquire(Some("a")).now_or_never().is_none());
+    }
+
+    #[test]
+    fn test_rejects_invalid_rates() {
+        for rate in [0.0, -1.0, f64::NAN] {
+            let limits = RateLimits {
+                namespaces: HashMap::from([("docs".to_string(), Rate { requests_per_sec: None, bytes_per_sec: Some(rate) })]),
+                ..Default::default()
+            };
+            assert!(matches!(RateLimiter::new(limits), Err(Error::InvalidArgument(_))));
+            assert!(RateLimiter::new(RateLimits { global: Some(requests(rate)), ..Default::default() }).is_err());
+        }
+    }
+}
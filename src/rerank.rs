@@ -0,0 +1,287 @@
+use serde_json::Value;
+use std::future::{Future, ready};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::types::parse_rfc3339;
+use crate::{DistanceMetric, Filter, MultiQueryResponse, QueryParams, QueryResponse, RankBy, Result, Row, Vector};
+
+/// A row with the score a `Reranker` orders it by; higher is better.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScoredRow {
+    pub row: Row,
+    pub score: f64,
+}
+
+/// A second ranking stage applied to query results, e.g. a cross-encoder.
+///
+/// `rerank` receives the hits in the server's order, scored by `relevance`,
+/// and returns them with updated scores; the caller sorts and truncates.
+/// Rerankers chain as tuples: `(RecencyDecay::new(..), AttributeBoost::new(..))`.
+pub trait Reranker {
+    fn rerank(&self, hits: Vec<ScoredRow>) -> impl Future<Output = Result<Vec<ScoredRow>>> + Send;
+}
+
+impl<A, B> Reranker for (A, B)
+where
+    A: Reranker + Sync,
+    B: Reranker + Sync,
+{
+    async fn rerank(&self, hits: Vec<ScoredRow>) -> Result<Vec<ScoredRow>> {
+        let hits = self.0.rerank(hits).await?;
+        self.1.rerank(hits).await
+    }
+}
+
+/// The initial score of the row at 0-based `rank` in results ranked by
+/// `rank_by`: `$dist` oriented so that higher is better, or `-rank` when the
+/// row has no `$dist`.
+pub(crate) fn relevance(rank_by: Option<&RankBy>, rank: usize, row: &Row) -> f64 {
+    match row.get("$dist").and_then(Value::as_f64) {
+        Some(dist) if rank_by.is_some_and(RankBy::ranks_by_distance) => -dist,
+        Some(score) => score,
+        None => -(rank as f64),
+    }
+}
+
+/// Rerank a query response and keep the best `top_k`. `rank_by` is the
+/// ranking the response was queried with. Ties keep the server's order.
+pub async fn rerank(
+    response: QueryResponse,
+    rank_by: Option<&RankBy>,
+    reranker: &impl Reranker,
+    top_k: usize,
+) -> Result<Vec<ScoredRow>> {
+    let hits = response
+        .rows
+        .into_iter()
+        .enumerate()
+        .map(|(rank, row)| ScoredRow { score: relevance(rank_by, rank, &row), row })
+        .collect();
+    let mut hits = reranker.rerank(hits).await?;
+    hits.sort_by(|a, b| b.score.total_cmp(&a.score));
+    hits.truncate(top_k);
+    Ok(hits)
+}
+
+/// `rerank` each result set of a multi-query response by the query it
+/// answers, keeping the best `top_k[i]` of the i-th set.
+pub async fn rerank_multi(
+    response: MultiQueryResponse,
+    queries: &[QueryParams],
+    reranker: &impl Reranker,
+    top_k: &[usize],
+) -> Result<Vec<Vec<ScoredRow>>> {
+    let mut results = Vec::with_capacity(response.results.len());
+    for ((response, query), top_k) in response.results.into_iter().zip(queries).zip(top_k) {
+        results.push(rerank(response, query.rank_by.as_ref(), reranker, *top_k).await?);
+    }
+    Ok(results)
+}
+
+/// Adds `weight * 0.5^(age / half_life)` to each score, where age is
+/// measured from a `datetime` attribute. Rows without a parseable timestamp
+/// get nothing. With a zero `half_life`, only rows stamped at or after `now`
+/// get the weight.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecencyDecay {
+    pub attr: String,
+    pub half_life: Duration,
+    pub weight: f64,
+    /// The time ages are measured from; the current time if unset.
+    pub now: Option<SystemTime>,
+}
+
+impl RecencyDecay {
+    pub fn new(attr: impl Into<String>, half_life: Duration) -> Self {
+        RecencyDecay { attr: attr.into(), half_life, weight: 1.0, now: None }
+    }
+
+    pub fn with_weight(mut self, weight: f64) -> Self {
+        self.weight = weight;
+        self
+    }
+}
+
+impl Reranker for RecencyDecay {
+    fn rerank(&self, mut hits: Vec<ScoredRow>) -> impl Future<Output = Result<Vec<ScoredRow>>> + Send {
+        let now = self.now.unwrap_or_else(SystemTime::now);
+        let now_ms = match now.duration_since(UNIX_EPOCH) {
+            Ok(d) => d.as_millis() as f64,
+            Err(e) => -(e.duration().as_millis() as f64),
+        };
+        // The smallest positive half-life keeps 0/0 from giving NaN; it
+        // decays any positive age to nothing.
+        let half_life_ms = (self.half_life.as_secs_f64() * 1000.0).max(f64::MIN_POSITIVE);
+        for hit in &mut hits {
+            let Some(Value::String(ts)) = hit.row.get(&self.attr) else {
+                continue;
+            };
+            let Some((seconds, nanos)) = parse_rfc3339(ts) else {
+                continue;
+            };
+            let ms = seconds as f64 * 1000.0 + nanos as f64 / 1e6;
+            let age = (now_ms - ms).max(0.0);
+            hit.score += self.weight * 0.5f64.powf(age / half_life_ms);
+        }
+        ready(Ok(hits))
+    }
+}
+
+/// Adds `boost` to the score of rows matching a filter, evaluated with
+/// `Filter::matches`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AttributeBoost {
+    pub filter: Filter,
+    pub boost: f64,
+}
+
+impl AttributeBoost {
+    pub fn new(filter: Filter, boost: f64) -> Self {
+        AttributeBoost { filter, boost }
+    }
+}
+
+impl Reranker for AttributeBoost {
+    fn rerank(&self, mut hits: Vec<ScoredRow>) -> impl Future<Output = Result<Vec<ScoredRow>>> + Send {
+        let filter = self.filter.compile();
+        for hit in hits.iter_mut().filter(|h| filter.matches(&h.row)) {
+            hit.score += self.boost;
+        }
+        ready(Ok(hits))
+    }
+}
+
+/// Replaces each score with the negated exact distance between `query` and
+/// the row's vector attribute, e.g. to re-score ANN candidates from a
+/// quantized index. The attribute must be in `include_attributes`; rows
+/// without it sink to the bottom.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExactRescore {
+    pub attr: String,
+    pub query: Vec<f32>,
+    pub metric: DistanceMetric,
+}
+
+impl ExactRescore {
+    pub fn new(attr: impl Into<String>, query: Vec<f32>, metric: DistanceMetric) -> Self {
+        ExactRescore { attr: attr.into(), query, metric }
+    }
+
+    fn distance(&self, v: &[f32]) -> Option<f64> {
+        if v.len() != self.query.len() {
+            return None;
+        }
+        let pairs = self.query.iter().zip(v).map(|(a, b)| (*a as f64, *b as f64));
+        Some(match self.metric {
+            DistanceMetric::EuclideanSquared => pairs.map(|(a, b)| (a - b) * (a - b)).sum(),
+            DistanceMetric::CosineDistance => {
+                let (dot, aa, bb) = pairs.fold((0.0, 0.0, 0.0), |(d, x, y), (a, b)| (d + a * b, x + a * a, y + b * b));
+                let norm = (aa * bb).sqrt();
+                if norm == 0.0 { 1.0 } else { 1.0 - dot / norm }
+            }
+        })
+    }
+}
+
+impl Reranker for ExactRescore {
+    fn rerank(&self, mut hits: Vec<ScoredRow>) -> impl Future<Output = Result<Vec<ScoredRow>>> + Send {
+        for hit in &mut hits {
+            let distance = hit
+                .row
+                .get(&self.attr)
+                .and_then(|v| Vector::try_from(v).ok())
+                .and_then(|v| self.distance(&v));
+            hit.score = distance.map_or(f64::NEG_INFINITY, |d| -d);
+        }
+        ready(Ok(hits))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn response(rows: Value) -> QueryResponse {
+        serde_json::from_value(json!({ "rows": rows })).unwrap()
+    }
+
+    fn ids(hits: &[ScoredRow]) -> Vec<Value> {
+        hits.iter().map(|h| h.row["id"].clone()).collect()
+    }
+
+    #[tokio::test]
+    async fn test_recency_and_boost() {
+        let response = response(json!([
+            {"id": 1, "$dist": 2.9, "ts": "2024-01-01T00:00:00Z", "tier": "free"},
+            {"id": 2, "$dist": 2.5, "ts": "2024-01-31T00:00:00Z", "tier": "free"},
+            {"id": 3, "$dist": 2.0, "ts": "2024-01-31T00:00:00Z", "tier": "pro"},
+        ]));
+        let decay = RecencyDecay {
+            now: Some(UNIX_EPOCH + Duration::from_millis(1_706_659_200_000)), // 2024-01-31
+            ..RecencyDecay::new("ts", Duration::from_secs(30 * 86400))
+        };
+        let reranker = (decay, AttributeBoost::new(Filter::eq("tier", "pro"), 0.75));
+        let hits = rerank(response, Some(&RankBy::bm25("text", "q")), &reranker, 2).await.unwrap();
+
+        assert_eq!(ids(&hits), vec![json!(3), json!(2)]);
+        assert_eq!(hits[0].score, 2.0 + 1.0 + 0.75);
+        assert_eq!(hits[1].score, 2.5 + 1.0);
+    }
+
+    #[tokio::test]
+    async fn test_zero_half_life() {
+        let response = response(json!([
+            {"id": 1, "$dist": 1.0, "ts": "2024-01-30T23:59:59.999Z"},
+            {"id": 2, "$dist": 1.0, "ts": "2024-01-31T00:00:00Z"},
+        ]));
+        let decay = RecencyDecay {
+            now: Some(UNIX_EPOCH + Duration::from_millis(1_706_659_200_000)),
+            ..RecencyDecay::new("ts", Duration::ZERO)
+        };
+        let hits = rerank(response, None, &decay, 2).await.unwrap();
+        assert_eq!(hits.iter().map(|h| h.score).collect::<Vec<_>>(), vec![2.0, 1.0]);
+    }
+
+    #[tokio::test]
+    async fn test_exact_rescore() {
+        let response = response(json!([
+            {"id": 1, "$dist": 0.1, "v": [0.0, 1.0]},
+            {"id": 2, "$dist": 0.2, "v": [1.0, 0.1]},
+            {"id": 3, "$dist": 0.3},
+        ]));
+        let rank_by = RankBy::vector("v", vec![1.0, 0.0]);
+        let reranker = ExactRescore::new("v", vec![1.0, 0.0], DistanceMetric::EuclideanSquared);
+        let hits = rerank(response, Some(&rank_by), &reranker, 10).await.unwrap();
+
+        assert_eq!(ids(&hits), vec![json!(2), json!(1), json!(3)]);
+        assert!((hits[0].score + 0.01).abs() < 1e-6);
+        assert_eq!(hits[2].score, f64::NEG_INFINITY);
+    }
+
+    #[tokio::test]
+    async fn test_rerank_multi() {
+        let response: MultiQueryResponse = serde_json::from_value(json!({"results": [
+            {"rows": [{"id": 1, "$dist": 0.1}, {"id": 2, "$dist": 0.2, "tier": "pro"}, {"id": 3, "$dist": 0.3}]},
+            {"rows": [{"id": 4, "$dist": 1.0}, {"id": 5, "$dist": 2.0, "tier": "pro"}, {"id": 6, "$dist": 3.0}]},
+        ]}))
+        .unwrap();
+        let queries = [
+            QueryParams { rank_by: Some(RankBy::vector("v", vec![1.0])), ..Default::default() },
+            QueryParams { rank_by: Some(RankBy::bm25("text", "q")), ..Default::default() },
+        ];
+        let boost = AttributeBoost::new(Filter::eq("tier", "pro"), 1.5);
+        let hits = rerank_multi(response, &queries, &boost, &[1, 2]).await.unwrap();
+
+        assert_eq!(ids(&hits[0]), vec![json!(2)]);
+        assert_eq!(ids(&hits[1]), vec![json!(5), json!(6)]);
+    }
+
+    #[test]
+    fn test_relevance_orientation() {
+        let row: Row = serde_json::from_value(json!({"$dist": 0.25})).unwrap();
+        assert_eq!(relevance(Some(&RankBy::vector("v", vec![1.0])), 0, &row), -0.25);
+        assert_eq!(relevance(Some(&RankBy::bm25("t", "q")), 0, &row), 0.25);
+        assert_eq!(relevance(None, 4, &Row::new()), -4.0);
+    }
+}
@@ -1,7 +1,7 @@
 use serde::Deserialize;
 use std::collections::HashMap;
 
-use crate::Row;
+use crate::{Id, Row, Timestamp};
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct WriteResponse {
@@ -20,18 +20,28 @@ pub struct WriteResponse {
     pub rows_remaining: Option<bool>,
 
     #[serde(default)]
-    pub upserted_ids: Option<Vec<serde_json::Value>>,
+    pub upserted_ids: Option<Vec<Id>>,
 
     #[serde(default)]
-    pub patched_ids: Option<Vec<serde_json::Value>>,
+    pub patched_ids: Option<Vec<Id>>,
 
     #[serde(default)]
-    pub deleted_ids: Option<Vec<serde_json::Value>>,
+    pub deleted_ids: Option<Vec<Id>>,
 
     #[serde(default)]
     pub billing: Option<WriteBilling>,
 }
 
+impl WriteResponse {
+    /// Logical bytes billed for the write and any query it ran.
+    pub(crate) fn billed_bytes(&self) -> u64 {
+        self.billing.as_ref().map_or(0, |b| {
+            let query = b.query.as_ref().map_or(0, |q| q.billable_logical_bytes_queried + q.billable_logical_bytes_returned);
+            b.billable_logical_bytes_written + query
+        })
+    }
+}
+
 #[derive(Debug, Clone, Deserialize)]
 pub struct WriteBilling {
     pub billable_logical_bytes_written: u64,
@@ -64,6 +74,13 @@ pub struct QueryResponse {
     pub performance: Option<QueryPerformance>,
 }
 
+impl QueryResponse {
+    /// Logical bytes billed for the query.
+    pub(crate) fn billed_bytes(&self) -> u64 {
+        self.billing.as_ref().map_or(0, |b| b.billable_logical_bytes_queried + b.billable_logical_bytes_returned)
+    }
+}
+
 #[derive(Debug, Clone, Deserialize)]
 pub struct QueryBilling {
     pub billable_logical_bytes_queried: u64,
@@ -104,10 +121,10 @@ pub struct DeleteAllResponse {
 #[derive(Debug, Clone, Deserialize)]
 pub struct NamespaceMetadata {
     #[serde(default)]
-    pub created_at: Option<String>,
+    pub created_at: Option<Timestamp>,
 
     #[serde(default)]
-    pub updated_at: Option<String>,
+    pub updated_at: Option<Timestamp>,
 
     #[serde(default)]
     pub approx_logical_bytes: Option<u64>,
@@ -194,6 +211,7 @@ mod tests {
         assert_eq!(resp.rows_patched, Some(3));
         assert_eq!(resp.rows_deleted, Some(2));
         assert_eq!(resp.upserted_ids.as_ref().unwrap().len(), 5);
+        assert_eq!(resp.upserted_ids.as_ref().unwrap()[0], Id::Uint(1));
         assert!(resp.billing.is_some());
     }
 
@@ -257,7 +275,7 @@ mod tests {
             "schema": { "id": { "type": "uint" } }
         }"#;
         let resp: NamespaceMetadata = serde_json::from_str(json).unwrap();
-        assert_eq!(resp.created_at, Some("2024-01-15T12:00:00Z".to_string()));
+        assert_eq!(resp.created_at.as_ref().map(Timestamp::as_str), Some("2024-01-15T12:00:00Z"));
         assert_eq!(resp.approx_row_count, Some(100));
         assert!(resp.encryption.is_some());
         assert_eq!(resp.encryption.unwrap().sse, Some(true));
@@ -0,0 +1,167 @@
+use futures::channel::oneshot;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+
+use crate::{Error, NamespaceMetadata, QueryResponse, Result};
+
+type Waiters<T> = Vec<oneshot::Sender<Result<T>>>;
+
+/// Coalesces concurrent calls with the same key into one request whose
+/// result is handed to every caller.
+pub(crate) struct SingleFlight<T> {
+    in_flight: Mutex<HashMap<String, Waiters<T>>>,
+}
+
+impl<T: Clone> SingleFlight<T> {
+    pub(crate) fn new() -> Self {
+        SingleFlight { in_flight: Mutex::new(HashMap::new()) }
+    }
+
+    /// Run `request` unless a call with the same key is already in flight, in
+    /// which case wait for its result instead. If that call is cancelled, the
+    /// waiters fall back to running `request` themselves.
+    pub(crate) async fn run<F>(&self, key: String, request: impl FnOnce() -> F) -> Result<T>
+    where
+        F: Future<Output = Result<T>>,
+    {
+        let waiter = {
+            let mut in_flight = self.in_flight.lock().unwrap();
+            match in_flight.get_mut(&key) {
+                Some(waiters) => {
+                    let (tx, rx) = oneshot::channel();
+                    waiters.push(tx);
+                    Some(rx)
+                }
+                None => {
+                    in_flight.insert(key.clone(), Vec::new());
+                    None
+                }
+            }
+        };
+        if let Some(rx) = waiter {
+            return match rx.await {
+                Ok(result) => result,
+                Err(oneshot::Canceled) => request().await,
+            };
+        }
+
+        let mut leader = Leader { flight: self, key: Some(key) };
+        let result = request().await;
+        let waiters = leader.finish();
+        match result {
+            Ok(value) => {
+                for tx in waiters {
+                    let _ = tx.send(Ok(value.clone()));
+                }
+                Ok(value)
+            }
+            Err(e) if waiters.is_empty() => Err(e),
+            Err(e) => {
+                let e = Arc::new(e);
+                for tx in waiters {
+                    let _ = tx.send(Err(Error::shared(&e)));
+                }
+                Err(Arc::try_unwrap(e).unwrap_or_else(|e| Error::shared(&e)))
+            }
+        }
+    }
+}
+
+/// Removes the in-flight entry even if the leading call is dropped, which
+/// drops the waiters' senders and wakes them.
+struct Leader<'a, T> {
+    flight: &'a SingleFlight<T>,
+    key: Option<String>,
+}
+
+impl<T> Leader<'_, T> {
+    fn finish(&mut self) -> Waiters<T> {
+        let key = self.key.take().expect("leader finishes once");
+        self.flight.in_flight.lock().unwrap().remove(&key).unwrap_or_default()
+    }
+}
+
+impl<T> Drop for Leader<'_, T> {
+    fn drop(&mut self) {
+        if let Some(key) = self.key.take() {
+            self.flight.in_flight.lock().unwrap().remove(&key);
+        }
+    }
+}
+
+/// The calls coalesced by `Client::with_single_flight`.
+pub(crate) struct SingleFlightGroups {
+    pub(crate) queries: SingleFlight<QueryResponse>,
+    pub(crate) metadata: SingleFlight<NamespaceMetadata>,
+}
+
+impl SingleFlightGroups {
+    pub(crate) fn new() -> Self {
+        SingleFlightGroups { queries: SingleFlight::new(), metadata: SingleFlight::new() }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[tokio::test]
+    async fn test_coalesces_concurrent_calls() {
+        let flight = SingleFlight::<u64>::new();
+        let calls = AtomicUsize::new(0);
+        let (release, released) = oneshot::channel::<()>();
+        let released = futures::future::FutureExt::shared(released);
+
+        let call = |n: u64| {
+            let released = released.clone();
+            let calls = &calls;
+            flight.run("key".to_string(), move || async move {
+                calls.fetch_add(1, Ordering::SeqCst);
+                released.await.unwrap();
+                Ok(n)
+            })
+        };
+        let all = futures::future::join3(call(1), call(2), call(3));
+        let (results, _) = futures::future::join(all, async { release.send(()).unwrap() }).await;
+
+        assert_eq!((results.0.unwrap(), results.1.unwrap(), results.2.unwrap()), (1, 1, 1));
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert!(flight.in_flight.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_shares_errors() {
+        let flight = SingleFlight::<u64>::new();
+        let (release, released) = oneshot::channel::<()>();
+        let released = futures::future::FutureExt::shared(released);
+        let call = || {
+            let released = released.clone();
+            flight.run("key".to_string(), move || async move {
+                released.await.unwrap();
+                Err(Error::Api { status: 404, message: "not found".to_string() })
+            })
+        };
+        let both = futures::future::join(call(), call());
+        let ((a, b), _) = futures::future::join(both, async { release.send(()).unwrap() }).await;
+        assert!(matches!(a, Err(Error::Api { status: 404, .. })));
+        assert!(matches!(b, Err(Error::Api { status: 404, .. })));
+    }
+
+    #[tokio::test]
+    async fn test_waiter_runs_request_when_leader_is_dropped() {
+        let flight = SingleFlight::<u64>::new();
+        let mut leader = Box::pin(flight.run("key".to_string(), futures::future::pending));
+        let mut waiter = Box::pin(flight.run("key".to_string(), || async { Ok(2) }));
+        futures::future::poll_fn(|cx| {
+            assert!(leader.as_mut().poll(cx).is_pending());
+            assert!(waiter.as_mut().poll(cx).is_pending());
+            std::task::Poll::Ready(())
+        })
+        .await;
+
+        drop(leader);
+        assert_eq!(waiter.await.unwrap(), 2);
+    }
+}
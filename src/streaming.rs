@@ -0,0 +1,238 @@
+use futures::stream::{self, Fuse, Stream, StreamExt};
+use serde::Serialize;
+use std::io::Write;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+
+#[cfg(any(feature = "gzip", feature = "zstd"))]
+use crate::compression::{Encoder, RequestCompression};
+use crate::{Result, WriteParams};
+
+/// Serialized bytes buffered before a chunk of the body is sent.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Where the serialized JSON goes: straight into the next chunk, or through
+/// a compressor.
+enum Sink {
+    Plain(Vec<u8>),
+    #[cfg(any(feature = "gzip", feature = "zstd"))]
+    Compressed(Encoder),
+}
+
+impl Sink {
+    fn write_all(&mut self, bytes: &[u8]) -> std::io::Result<()> {
+        match self {
+            Sink::Plain(buf) => buf.write_all(bytes),
+            #[cfg(any(feature = "gzip", feature = "zstd"))]
+            Sink::Compressed(encoder) => encoder.write_all(bytes),
+        }
+    }
+
+    /// The output ready to send.
+    fn take(&mut self) -> Vec<u8> {
+        match self {
+            Sink::Plain(buf) => std::mem::take(buf),
+            #[cfg(any(feature = "gzip", feature = "zstd"))]
+            Sink::Compressed(encoder) => encoder.take_output(),
+        }
+    }
+
+    fn finish(self) -> std::io::Result<Vec<u8>> {
+        match self {
+            Sink::Plain(buf) => Ok(buf),
+            #[cfg(any(feature = "gzip", feature = "zstd"))]
+            Sink::Compressed(encoder) => encoder.finish(),
+        }
+    }
+}
+
+/// The error that ended a streamed body, if any. reqwest reports a failed
+/// body like a failure to reach the server, so the cause is kept here for
+/// the client to return instead.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct BodyError(Arc<Mutex<Option<serde_json::Error>>>);
+
+impl BodyError {
+    /// Keep `e` and return an error for the body stream to end with.
+    fn fail(&self, e: serde_json::Error) -> std::io::Error {
+        let message = e.to_string();
+        *self.0.lock().unwrap() = Some(e);
+        std::io::Error::other(message)
+    }
+
+    pub(crate) fn take(&self) -> Option<serde_json::Error> {
+        self.0.lock().unwrap().take()
+    }
+}
+
+struct BodyState<S> {
+    rows: Pin<Box<Fuse<S>>>,
+    sink: Option<Sink>,
+    /// Bytes written to the sink since the last chunk.
+    pending: usize,
+    first: bool,
+    error: BodyError,
+}
+
+/// The row's JSON, preceded by a comma unless it is the first.
+fn row_json(row: &impl Serialize, first: &mut bool) -> serde_json::Result<Vec<u8>> {
+    let mut json = if *first { Vec::new() } else { vec![b','] };
+    *first = false;
+    serde_json::to_writer(&mut json, row)?;
+    Ok(json)
+}
+
+/// The JSON of `template` with `upsert_rows` taken from `rows`, serialized
+/// lazily in chunks of about `CHUNK_SIZE` bytes, so only one chunk and one
+/// row are held at a time, and its `Content-Encoding`.
+///
+/// With compression, rows are serialized up front until the body reaches
+/// `min_size`, since the encoding is sent before the body; smaller bodies
+/// are sent uncompressed. A row that fails to serialize before then fails
+/// here; after, it ends the stream with an error, failing the request, and
+/// is kept in the returned `BodyError`.
+pub(crate) async fn write_body<S>(
+    mut template: WriteParams,
+    rows: S,
+    #[cfg(any(feature = "gzip", feature = "zstd"))] compression: Option<&RequestCompression>,
+) -> Result<(impl Stream<Item = std::io::Result<Vec<u8>>> + Send + 'static, Option<&'static str>, BodyError)>
+where
+    S: Stream + Send + 'static,
+    S::Item: Serialize,
+{
+    template.upsert_rows = None;
+    let mut head = serde_json::to_vec(&template)?;
+    head.pop();
+    if head.len() > 1 {
+        head.push(b',');
+    }
+    head.extend_from_slice(br#""upsert_rows":["#);
+
+    let mut state =
+        BodyState { rows: Box::pin(rows.fuse()), sink: None, pending: 0, first: true, error: BodyError::default() };
+    #[cfg(any(feature = "gzip", feature = "zstd"))]
+    if let Some(config) = compression {
+        while head.len() < config.min_size
+            && let Some(row) = state.rows.next().await
+        {
+            head.extend(row_json(&row, &mut state.first)?);
+        }
+    }
+    state.pending = head.len();
+
+    #[cfg(any(feature = "gzip", feature = "zstd"))]
+    let (sink, content_encoding) = match compression {
+        Some(config) if head.len() >= config.min_size => {
+            let mut encoder = config.algorithm.encoder(Vec::new()).map_err(serde_json::Error::io)?;
+            encoder.write_all(&head).map_err(serde_json::Error::io)?;
+            (Sink::Compressed(encoder), Some(config.algorithm.content_encoding()))
+        }
+        _ => (Sink::Plain(head), None),
+    };
+    #[cfg(not(any(feature = "gzip", feature = "zstd")))]
+    let (sink, content_encoding) = (Sink::Plain(head), None);
+    state.sink = Some(sink);
+
+    let error = state.error.clone();
+    let body = stream::unfold(state, |mut state| async move {
+        let sink = state.sink.as_mut()?;
+        while let Some(row) = state.rows.next().await {
+            let written = row_json(&row, &mut state.first).and_then(|json| {
+                sink.write_all(&json).map_err(serde_json::Error::io)?;
+                Ok(json.len())
+            });
+            match written {
+                Ok(n) => state.pending += n,
+                Err(e) => {
+                    state.sink = None;
+                    let e = state.error.fail(e);
+                    return Some((Err(e), state));
+                }
+            }
+            if state.pending >= CHUNK_SIZE {
+                state.pending = 0;
+                let chunk = sink.take();
+                if !chunk.is_empty() {
+                    return Some((Ok(chunk), state));
+                }
+            }
+        }
+        let mut sink = state.sink.take()?;
+        let tail = sink.write_all(b"]}").and_then(|()| sink.finish());
+        let tail = tail.map_err(|e| state.error.fail(serde_json::Error::io(e)));
+        Some((tail, state))
+    });
+    Ok((body, content_encoding, error))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DistanceMetric;
+    use futures::TryStreamExt;
+
+    async fn collect(body: impl Stream<Item = std::io::Result<Vec<u8>>>) -> Vec<Vec<u8>> {
+        body.try_collect().await.unwrap()
+    }
+
+    async fn write_body<S>(template: WriteParams, rows: S) -> impl Stream<Item = std::io::Result<Vec<u8>>>
+    where
+        S: Stream + Send + 'static,
+        S::Item: Serialize,
+    {
+        super::write_body(
+            template,
+            rows,
+            #[cfg(any(feature = "gzip", feature = "zstd"))]
+            None,
+        )
+        .await
+        .unwrap()
+        .0
+    }
+
+    #[tokio::test]
+    async fn test_body_matches_write_params() {
+        let rows: Vec<_> = (0..3u64).map(|i| serde_json::json!({"id": i, "v": [0.5, 1.5]})).collect();
+        let template = WriteParams { distance_metric: Some(DistanceMetric::CosineDistance), ..Default::default() };
+        let body = collect(write_body(template, stream::iter(rows.clone())).await).await.concat();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json, serde_json::json!({"distance_metric": "cosine_distance", "upsert_rows": rows}));
+
+        let body = collect(write_body(WriteParams::default(), stream::empty::<u64>()).await).await.concat();
+        assert_eq!(body, br#"{"upsert_rows":[]}"#);
+    }
+
+    #[tokio::test]
+    async fn test_body_is_chunked() {
+        let rows = stream::iter(0..20_000u64).map(|i| serde_json::json!({"id": i, "text": "some text"}));
+        let chunks = collect(write_body(WriteParams::default(), rows).await).await;
+        assert!(chunks.len() > 4);
+        assert!(chunks.iter().all(|c| c.len() < 2 * CHUNK_SIZE));
+        let json: serde_json::Value = serde_json::from_slice(&chunks.concat()).unwrap();
+        assert_eq!(json["upsert_rows"].as_array().unwrap().len(), 20_000);
+    }
+
+    #[cfg(feature = "gzip")]
+    #[tokio::test]
+    async fn test_compressed_body() {
+        use crate::Compression;
+        use std::io::Read;
+
+        let config = RequestCompression { algorithm: Compression::Gzip, min_size: 1024 };
+        let rows = stream::iter(0..5_000u64).map(|i| serde_json::json!({"id": i}));
+        let (body, encoding, _) = super::write_body(WriteParams::default(), rows, Some(&config)).await.unwrap();
+        assert_eq!(encoding, Some("gzip"));
+        let compressed = collect(body).await.concat();
+        let mut out = Vec::new();
+        flate2::read::GzDecoder::new(&compressed[..]).read_to_end(&mut out).unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&out).unwrap();
+        assert_eq!(json["upsert_rows"][4_999]["id"], 4_999);
+
+        // Bodies under `min_size` go uncompressed.
+        let rows = stream::iter(0..3u64).map(|i| serde_json::json!({"id": i}));
+        let (body, encoding, _) = super::write_body(WriteParams::default(), rows, Some(&config)).await.unwrap();
+        assert_eq!(encoding, None);
+        assert_eq!(collect(body).await.concat(), br#"{"upsert_rows":[{"id":0},{"id":1},{"id":2}]}"#);
+    }
+}
@@ -0,0 +1,363 @@
+use futures::StreamExt;
+use futures::stream::FuturesUnordered;
+use std::collections::VecDeque;
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+use crate::{Error, Result, Row, WriteResponse};
+
+/// Settings for `Namespace::upsert_rows_adaptive`.
+///
+/// Batch size and concurrency grow additively while writes succeed within
+/// `target_latency`, and halve on a 429 or a slower response (AIMD), so a
+/// bulk load settles near the fastest rate the namespace sustains.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AdaptiveWriteConfig {
+    pub initial_batch_size: usize,
+    pub min_batch_size: usize,
+    pub max_batch_size: usize,
+    pub initial_concurrency: usize,
+    pub max_concurrency: usize,
+    /// Responses slower than this count as backpressure.
+    pub target_latency: Duration,
+    /// Wait after a 429, doubled for each consecutive one.
+    pub backoff: Duration,
+    /// Consecutive 429s tolerated before giving up.
+    pub max_retries: u32,
+    /// After a 429, poll `metadata()` until the index has caught up before
+    /// resuming.
+    pub wait_for_index: bool,
+    pub poll_interval: Duration,
+}
+
+impl Default for AdaptiveWriteConfig {
+    fn default() -> Self {
+        AdaptiveWriteConfig {
+            initial_batch_size: 1000,
+            min_batch_size: 100,
+            max_batch_size: 10_000,
+            initial_concurrency: 1,
+            max_concurrency: 8,
+            target_latency: Duration::from_secs(5),
+            backoff: Duration::from_secs(1),
+            max_retries: 8,
+            wait_for_index: false,
+            poll_interval: Duration::from_secs(1),
+        }
+    }
+}
+
+impl AdaptiveWriteConfig {
+    fn validate(&self) -> Result<()> {
+        if self.max_batch_size == 0 || self.max_batch_size < self.min_batch_size {
+            return Err(Error::InvalidArgument(format!(
+                "max_batch_size {} must be positive and at least min_batch_size {}",
+                self.max_batch_size, self.min_batch_size
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// What an adaptive write did, including the batch size and concurrency it
+/// settled at.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AdaptiveWriteReport {
+    pub rows_written: u64,
+    pub requests: u64,
+    pub throttled: u64,
+    pub batch_size: usize,
+    pub concurrency: usize,
+}
+
+/// Additive-increase/multiplicative-decrease control of batch size and
+/// concurrency.
+struct Aimd<'a> {
+    config: &'a AdaptiveWriteConfig,
+    batch_size: f64,
+    concurrency: f64,
+    /// Bumped on every decrease. Responses to requests sent before it don't
+    /// decrease again, so one congestion event halves only once.
+    epoch: u64,
+}
+
+impl<'a> Aimd<'a> {
+    fn new(config: &'a AdaptiveWriteConfig) -> Self {
+        Aimd {
+            config,
+            batch_size: config.initial_batch_size.clamp(config.min_batch_size.max(1), config.max_batch_size) as f64,
+            concurrency: config.initial_concurrency.clamp(1, config.max_concurrency.max(1)) as f64,
+            epoch: 0,
+        }
+    }
+
+    fn batch_size(&self) -> usize {
+        self.batch_size as usize
+    }
+
+    fn concurrency(&self) -> usize {
+        self.concurrency as usize
+    }
+
+    fn on_success(&mut self, latency: Duration, epoch: u64) {
+        if latency > self.config.target_latency {
+            self.decrease(epoch);
+            return;
+        }
+        // Roughly +1 concurrency per round of requests, as in TCP.
+        self.concurrency = (self.concurrency + 1.0 / self.concurrency).min(self.config.max_concurrency.max(1) as f64);
+        self.batch_size = (self.batch_size + self.config.min_batch_size.max(1) as f64).min(self.config.max_batch_size as f64);
+    }
+
+    fn decrease(&mut self, epoch: u64) {
+        if epoch < self.epoch {
+            return;
+        }
+        self.epoch += 1;
+        self.concurrency = (self.concurrency / 2.0).max(1.0);
+        self.batch_size = (self.batch_size / 2.0).max(self.config.min_batch_size.max(1) as f64);
+    }
+}
+
+/// Write `rows` in adaptively sized, concurrent batches. Throttled batches
+/// are retried after a backoff, and other errors abort.
+///
+/// `write` sends a batch and hands it back with the result, so a throttled
+/// batch can be retried without copying every batch before it is sent.
+///
+/// A retry waits inside its own future, alongside the writes still in
+/// flight, so those keep being driven while it backs off. No new batches
+/// start until every pending retry has been answered.
+///
+/// Fails with `Error::InvalidArgument` if `max_batch_size` is zero or below
+/// `min_batch_size`.
+pub(crate) async fn write_adaptive<W, WF, I, IF>(
+    rows: Vec<Row>,
+    config: &AdaptiveWriteConfig,
+    write: W,
+    wait_for_index: I,
+) -> Result<AdaptiveWriteReport>
+where
+    W: Fn(Vec<Row>) -> WF,
+    WF: Future<Output = (Result<WriteResponse>, Vec<Row>)>,
+    I: Fn() -> IF,
+    IF: Future<Output = Result<()>>,
+{
+    config.validate()?;
+    let mut pending: VecDeque<Row> = rows.into();
+    let mut aimd = Aimd::new(config);
+    let mut in_flight = FuturesUnordered::new();
+    let mut report = AdaptiveWriteReport::default();
+    let mut consecutive_throttles = 0;
+    let mut retrying = 0;
+
+    // Send a batch, after backing off for `delay` if it is a retry.
+    let send = |batch: Vec<Row>, epoch: u64, delay: Option<Duration>| {
+        let (write, wait_for_index) = (&write, &wait_for_index);
+        async move {
+            if let Some(delay) = delay {
+                tokio::time::sleep(delay).await;
+                if config.wait_for_index
+                    && let Err(e) = wait_for_index().await
+                {
+                    return (Err(e), batch, epoch, Instant::now(), true);
+                }
+            }
+            let started = Instant::now();
+            let (result, batch) = write(batch).await;
+            (result, batch, epoch, started, delay.is_some())
+        }
+    };
+
+    loop {
+        while retrying == 0 && in_flight.len() < aimd.concurrency() && !pending.is_empty() {
+            let n = aimd.batch_size().min(pending.len());
+            in_flight.push(send(pending.drain(..n).collect(), aimd.epoch, None));
+            report.requests += 1;
+        }
+
+        let Some((result, batch, epoch, started, retry)) = in_flight.next().await else {
+            break;
+        };
+        if retry {
+            retrying -= 1;
+        }
+        match result {
+            Ok(_) => {
+                consecutive_throttles = 0;
+                report.rows_written += batch.len() as u64;
+                aimd.on_success(started.elapsed(), epoch);
+            }
+            Err(Error::Api { status: 429, message }) => {
+                report.throttled += 1;
+                consecutive_throttles += 1;
+                if consecutive_throttles > config.max_retries {
+                    return Err(Error::Api { status: 429, message });
+                }
+                aimd.decrease(epoch);
+                let delay = config.backoff * 2u32.saturating_pow(consecutive_throttles - 1);
+                in_flight.push(send(batch, aimd.epoch, Some(delay)));
+                retrying += 1;
+                report.requests += 1;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+
+    report.batch_size = aimd.batch_size();
+    report.concurrency = aimd.concurrency();
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn rows(n: u64) -> Vec<Row> {
+        (0..n).map(|i| Row::from([("id".to_string(), i.into())])).collect()
+    }
+
+    fn ok() -> Result<WriteResponse> {
+        Ok(serde_json::from_value(serde_json::json!({"rows_affected": 0})).unwrap())
+    }
+
+    fn config() -> AdaptiveWriteConfig {
+        AdaptiveWriteConfig {
+            initial_batch_size: 10,
+            min_batch_size: 5,
+            max_batch_size: 40,
+            max_concurrency: 4,
+            backoff: Duration::from_millis(1),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_aimd() {
+        let config = config();
+        let mut aimd = Aimd::new(&config);
+        for _ in 0..20 {
+            aimd.on_success(Duration::ZERO, aimd.epoch);
+        }
+        assert_eq!((aimd.batch_size(), aimd.concurrency()), (40, 4));
+
+        let epoch = aimd.epoch;
+        aimd.decrease(epoch);
+        // A second response from the same round doesn't halve again.
+        aimd.decrease(epoch);
+        assert_eq!((aimd.batch_size(), aimd.concurrency()), (20, 2));
+
+        aimd.on_success(Duration::from_secs(60), aimd.epoch);
+        assert_eq!((aimd.batch_size(), aimd.concurrency()), (10, 1));
+    }
+
+    #[tokio::test]
+    async fn test_retries_throttled_batches() {
+        let calls = AtomicUsize::new(0);
+        let written = Mutex::new(Vec::new());
+        let report = write_adaptive(
+            rows(100),
+            &config(),
+            |batch| {
+                let n = calls.fetch_add(1, Ordering::SeqCst);
+                let written = &written;
+                async move {
+                    if n == 2 {
+                        return (Err(Error::Api { status: 429, message: "slow down".to_string() }), batch);
+                    }
+                    written.lock().unwrap().extend(batch.iter().map(|r| r["id"].as_u64().unwrap()));
+                    (ok(), batch)
+                }
+            },
+            || async { Ok(()) },
+        )
+        .await
+        .unwrap();
+
+        let mut written = written.into_inner().unwrap();
+        written.sort();
+        assert_eq!(written, (0..100).collect::<Vec<_>>());
+        assert_eq!(report.rows_written, 100);
+        assert_eq!(report.throttled, 1);
+    }
+
+    #[tokio::test]
+    async fn test_backoff_keeps_driving_in_flight_writes() {
+        let config = AdaptiveWriteConfig {
+            initial_batch_size: 5,
+            initial_concurrency: 2,
+            backoff: Duration::from_millis(300),
+            ..config()
+        };
+        let calls = AtomicUsize::new(0);
+        let finished = Mutex::new(None);
+        let started = Instant::now();
+        write_adaptive(
+            rows(10),
+            &config,
+            |batch| {
+                let n = calls.fetch_add(1, Ordering::SeqCst);
+                let finished = &finished;
+                async move {
+                    let result = match n {
+                        0 => Err(Error::Api { status: 429, message: String::new() }),
+                        1 => {
+                            tokio::time::sleep(Duration::from_millis(50)).await;
+                            *finished.lock().unwrap() = Some(started.elapsed());
+                            ok()
+                        }
+                        _ => ok(),
+                    };
+                    (result, batch)
+                }
+            },
+            || async { Ok(()) },
+        )
+        .await
+        .unwrap();
+
+        // The second write completes while the first backs off.
+        assert!(finished.into_inner().unwrap().unwrap() < Duration::from_millis(250));
+        assert!(started.elapsed() >= Duration::from_millis(300));
+    }
+
+    #[tokio::test]
+    async fn test_gives_up_and_propagates_errors() {
+        let throttled = write_adaptive(
+            rows(10),
+            &config(),
+            |batch| async { (Err(Error::Api { status: 429, message: String::new() }), batch) },
+            || async { Ok(()) },
+        )
+        .await;
+        assert!(matches!(throttled, Err(Error::Api { status: 429, .. })));
+
+        let failed = write_adaptive(
+            rows(10),
+            &config(),
+            |batch| async { (Err(Error::Api { status: 400, message: String::new() }), batch) },
+            || async { Ok(()) },
+        )
+        .await;
+        assert!(matches!(failed, Err(Error::Api { status: 400, .. })));
+    }
+
+    #[tokio::test]
+    async fn test_rejects_invalid_batch_sizes() {
+        for config in [
+            AdaptiveWriteConfig { max_batch_size: 50, ..Default::default() },
+            AdaptiveWriteConfig { min_batch_size: 0, max_batch_size: 0, ..Default::default() },
+        ] {
+            let result = write_adaptive(
+                rows(10),
+                &config,
+                |batch| async { (ok(), batch) },
+                || async { Ok(()) },
+            )
+            .await;
+            assert!(matches!(result, Err(Error::InvalidArgument(_))));
+        }
+    }
+}
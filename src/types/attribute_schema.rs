@@ -0,0 +1,85 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Schema for a single attribute, as used in `WriteParams::schema` and
+/// returned by `Namespace::schema()`.
+///
+/// Converts into `serde_json::Value`, so it can be inserted directly into the
+/// `WriteParams::schema` map.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct AttributeSchema {
+    #[serde(rename = "type", default, skip_serializing_if = "Option::is_none")]
+    pub attr_type: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub filterable: Option<bool>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub full_text_search: Option<serde_json::Value>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub regex: Option<bool>,
+
+    /// Any other schema options, passed through as-is.
+    #[serde(flatten)]
+    pub other: HashMap<String, serde_json::Value>,
+}
+
+impl AttributeSchema {
+    pub fn new(attr_type: impl Into<String>) -> Self {
+        AttributeSchema {
+            attr_type: Some(attr_type.into()),
+            ..Default::default()
+        }
+    }
+
+    /// A `[dims]f32` vector attribute.
+    pub fn f32_vector(dims: usize) -> Self {
+        AttributeSchema::new(format!("[{}]f32", dims))
+    }
+
+    /// A `[dims]f16` vector attribute.
+    pub fn f16_vector(dims: usize) -> Self {
+        AttributeSchema::new(format!("[{}]f16", dims))
+    }
+
+    /// The dimensions and element type of a vector attribute, e.g. `(1536, "f16")`
+    /// for `[1536]f16`.
+    pub fn vector_dims(&self) -> Option<(usize, &str)> {
+        let rest = self.attr_type.as_deref()?.strip_prefix('[')?;
+        let (dims, element) = rest.split_once(']')?;
+        match element {
+            "f32" | "f16" => Some((dims.parse().ok()?, element)),
+            _ => None,
+        }
+    }
+}
+
+impl From<AttributeSchema> for serde_json::Value {
+    fn from(v: AttributeSchema) -> Self {
+        serde_json::to_value(v).expect("attribute schema serializes to JSON")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_vector_schema_serialization() {
+        let value: serde_json::Value = AttributeSchema::f16_vector(512).into();
+        assert_eq!(value, serde_json::json!({"type": "[512]f16"}));
+    }
+
+    #[test]
+    fn test_schema_deserialization() {
+        let schema: AttributeSchema = serde_json::from_value(serde_json::json!({
+            "type": "[1536]f32",
+            "ann": true,
+        }))
+        .unwrap();
+        assert_eq!(schema.vector_dims(), Some((1536, "f32")));
+        assert_eq!(schema.other.get("ann"), Some(&serde_json::json!(true)));
+        assert_eq!(AttributeSchema::new("[]string").vector_dims(), None);
+    }
+}
@@ -0,0 +1,23 @@
+use half::f16;
+
+use super::vector::packed_vector;
+
+/// An f16 vector attribute, for namespaces with `[N]f16` vector schemas.
+///
+/// Like `Vector`, serializes as base64 of packed little-endian values and
+/// deserializes from either a base64 string or a float array.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct F16Vector(Vec<f16>);
+
+packed_vector!(F16Vector, f16);
+
+impl F16Vector {
+    /// Convert f32 values to f16, rounding to the nearest representable value.
+    pub fn from_f32_slice(values: &[f32]) -> Self {
+        F16Vector(values.iter().map(|v| f16::from_f32(*v)).collect())
+    }
+
+    pub fn to_f32_vec(&self) -> Vec<f32> {
+        self.0.iter().map(|v| v.to_f32()).collect()
+    }
+}
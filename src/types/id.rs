@@ -1,38 +1,204 @@
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+use crate::Error;
+
+/// A document ID: an unsigned integer, a UUID (with the `uuid` feature) or a string.
+///
+/// With the `uuid` feature enabled, strings in the canonical lowercase
+/// hyphenated UUID form, whether deserialized or converted with `From`, become
+/// `Id::Uuid`. Any other string, including other UUID spellings, stays an
+/// `Id::String`, so IDs always serialize back exactly as they were written.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]
 #[serde(untagged)]
 pub enum Id {
     Uint(u64),
+    #[cfg(feature = "uuid")]
+    Uuid(uuid::Uuid),
     String(String),
 }
 
+impl Id {
+    fn from_string(s: String) -> Id {
+        #[cfg(feature = "uuid")]
+        if let Ok(uuid) = uuid::Uuid::try_parse(&s)
+            && uuid.hyphenated().to_string() == s
+        {
+            return Id::Uuid(uuid);
+        }
+        Id::String(s)
+    }
+}
+
+impl<'de> Deserialize<'de> for Id {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Raw {
+            Uint(u64),
+            String(String),
+        }
+        Ok(match Raw::deserialize(deserializer)? {
+            Raw::Uint(v) => Id::Uint(v),
+            Raw::String(s) => Id::from_string(s),
+        })
+    }
+}
+
+impl std::fmt::Display for Id {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Id::Uint(v) => write!(f, "{}", v),
+            #[cfg(feature = "uuid")]
+            Id::Uuid(v) => write!(f, "{}", v),
+            Id::String(v) => f.write_str(v),
+        }
+    }
+}
+
 impl From<u64> for Id {
     fn from(v: u64) -> Self {
         Id::Uint(v)
     }
 }
 
-impl From<i64> for Id {
-    fn from(v: i64) -> Self {
-        Id::Uint(v as u64)
+impl From<u32> for Id {
+    fn from(v: u32) -> Self {
+        Id::Uint(v.into())
+    }
+}
+
+impl From<u16> for Id {
+    fn from(v: u16) -> Self {
+        Id::Uint(v.into())
     }
 }
 
-impl From<i32> for Id {
-    fn from(v: i32) -> Self {
-        Id::Uint(v as u64)
+impl From<u8> for Id {
+    fn from(v: u8) -> Self {
+        Id::Uint(v.into())
+    }
+}
+
+impl TryFrom<i64> for Id {
+    type Error = Error;
+
+    fn try_from(v: i64) -> Result<Self, Self::Error> {
+        u64::try_from(v)
+            .map(Id::Uint)
+            .map_err(|_| Error::InvalidId(format!("negative integer {}", v)))
+    }
+}
+
+impl TryFrom<i32> for Id {
+    type Error = Error;
+
+    fn try_from(v: i32) -> Result<Self, Self::Error> {
+        Id::try_from(i64::from(v))
+    }
+}
+
+impl TryFrom<usize> for Id {
+    type Error = Error;
+
+    fn try_from(v: usize) -> Result<Self, Self::Error> {
+        u64::try_from(v)
+            .map(Id::Uint)
+            .map_err(|_| Error::InvalidId(format!("integer {} does not fit in u64", v)))
     }
 }
 
 impl From<&str> for Id {
     fn from(v: &str) -> Self {
-        Id::String(v.to_string())
+        Id::from_string(v.to_string())
     }
 }
 
 impl From<String> for Id {
     fn from(v: String) -> Self {
-        Id::String(v)
+        Id::from_string(v)
+    }
+}
+
+#[cfg(feature = "uuid")]
+impl From<uuid::Uuid> for Id {
+    fn from(v: uuid::Uuid) -> Self {
+        Id::Uuid(v)
+    }
+}
+
+impl TryFrom<serde_json::Value> for Id {
+    type Error = Error;
+
+    fn try_from(v: serde_json::Value) -> Result<Self, Self::Error> {
+        serde_json::from_value(v.clone()).map_err(|_| Error::InvalidId(v.to_string()))
+    }
+}
+
+impl From<Id> for serde_json::Value {
+    fn from(v: Id) -> Self {
+        match v {
+            Id::Uint(v) => serde_json::Value::from(v),
+            #[cfg(feature = "uuid")]
+            Id::Uuid(v) => serde_json::Value::String(v.to_string()),
+            Id::String(v) => serde_json::Value::String(v),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_id_serialization() {
+        assert_eq!(serde_json::to_string(&Id::from(42u64)).unwrap(), "42");
+        assert_eq!(serde_json::to_string(&Id::from("doc-1")).unwrap(), r#""doc-1""#);
+    }
+
+    #[test]
+    fn test_id_deserialization() {
+        let ids: Vec<Id> = serde_json::from_str(r#"[1, "two"]"#).unwrap();
+        assert_eq!(ids, vec![Id::Uint(1), Id::String("two".to_string())]);
+    }
+
+    #[test]
+    fn test_negative_integer_rejected() {
+        assert!(Id::try_from(-1i64).is_err());
+        assert_eq!(Id::try_from(7i32).unwrap(), Id::Uint(7));
+    }
+
+    #[test]
+    fn test_id_from_value() {
+        assert_eq!(Id::try_from(serde_json::json!(3)).unwrap(), Id::Uint(3));
+        assert!(Id::try_from(serde_json::json!(-3)).is_err());
+        assert!(Id::try_from(serde_json::json!([1])).is_err());
+    }
+
+    #[cfg(feature = "uuid")]
+    #[test]
+    fn test_uuid_round_trip() {
+        let uuid = uuid::Uuid::parse_str("67e55044-10b1-426f-9247-bb680e5fe0c8").unwrap();
+        let json = serde_json::to_string(&Id::from(uuid)).unwrap();
+        assert_eq!(json, r#""67e55044-10b1-426f-9247-bb680e5fe0c8""#);
+        let id: Id = serde_json::from_str(&json).unwrap();
+        assert_eq!(id, Id::Uuid(uuid));
+    }
+
+    #[cfg(feature = "uuid")]
+    #[test]
+    fn test_non_canonical_uuid_strings_are_kept() {
+        for s in [
+            "67e5504410b1426f9247bb680e5fe0c8",
+            "67E55044-10B1-426F-9247-BB680E5FE0C8",
+            "{67e55044-10b1-426f-9247-bb680e5fe0c8}",
+            "urn:uuid:67e55044-10b1-426f-9247-bb680e5fe0c8",
+        ] {
+            let json = serde_json::to_string(s).unwrap();
+            let id: Id = serde_json::from_str(&json).unwrap();
+            assert_eq!(id, Id::String(s.to_string()));
+            assert_eq!(serde_json::to_string(&id).unwrap(), json);
+            assert_eq!(Id::from(s), id);
+        }
+        assert!(matches!(Id::from("67e55044-10b1-426f-9247-bb680e5fe0c8"), Id::Uuid(_)));
     }
 }
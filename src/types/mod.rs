@@ -1,9 +1,22 @@
+mod attribute_schema;
 mod distance_metric;
+#[cfg(feature = "half")]
+mod f16_vector;
 mod id;
 mod row;
+mod timestamp;
+mod vector;
 mod vector_encoding;
 
+pub use attribute_schema::AttributeSchema;
 pub use distance_metric::DistanceMetric;
+#[cfg(feature = "half")]
+pub use f16_vector::F16Vector;
 pub use id::Id;
-pub use row::Row;
+pub use row::{Row, RowExt};
+pub use timestamp::Timestamp;
+pub(crate) use timestamp::parse_rfc3339;
+pub use vector::Vector;
+#[cfg(feature = "ndarray")]
+pub(crate) use vector::array_into_vec;
 pub use vector_encoding::VectorEncoding;
@@ -1,3 +1,55 @@
 use std::collections::HashMap;
 
+use crate::Vector;
+
 pub type Row = HashMap<String, serde_json::Value>;
+
+/// Typed accessors for attributes of a `Row`.
+pub trait RowExt {
+    /// The attribute as an f32 vector, decoded from base64 or a float array
+    /// depending on the requested `VectorEncoding`. `None` if it is missing
+    /// or null.
+    fn vector(&self, attr: &str) -> crate::Result<Option<Vector>>;
+
+    /// The attribute as an f16 vector, decoded like `vector`.
+    #[cfg(feature = "half")]
+    fn f16_vector(&self, attr: &str) -> crate::Result<Option<crate::F16Vector>>;
+}
+
+impl RowExt for Row {
+    fn vector(&self, attr: &str) -> crate::Result<Option<Vector>> {
+        match self.get(attr) {
+            None | Some(serde_json::Value::Null) => Ok(None),
+            Some(v) => Vector::try_from(v).map(Some),
+        }
+    }
+
+    #[cfg(feature = "half")]
+    fn f16_vector(&self, attr: &str) -> crate::Result<Option<crate::F16Vector>> {
+        match self.get(attr) {
+            None | Some(serde_json::Value::Null) => Ok(None),
+            Some(v) => crate::F16Vector::try_from(v).map(Some),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_vector_accessor() {
+        let row: Row = serde_json::from_value(serde_json::json!({
+            "b64": "AACAPwAAIMA=",
+            "floats": [1.0, -2.5],
+            "null": null,
+            "text": 3,
+        }))
+        .unwrap();
+        assert_eq!(row.vector("b64").unwrap().unwrap().as_slice(), [1.0, -2.5]);
+        assert_eq!(row.vector("floats").unwrap(), row.vector("b64").unwrap());
+        assert!(row.vector("null").unwrap().is_none());
+        assert!(row.vector("missing").unwrap().is_none());
+        assert!(row.vector("text").is_err());
+    }
+}
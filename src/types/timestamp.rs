@@ -0,0 +1,315 @@
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// A turbopuffer `datetime` value.
+///
+/// Turbopuffer exchanges datetimes as RFC 3339 strings with millisecond
+/// precision in UTC (e.g. `2024-01-15T12:00:00.000Z`). A `Timestamp` keeps the
+/// wire string as-is and converts to and from `chrono` / `time` types when the
+/// matching cargo feature is enabled. With either feature, the string is
+/// parsed when the `Timestamp` is created, and deserializing an invalid one
+/// fails.
+///
+/// `Timestamp` doesn't implement `Eq` or `Ord`: the same instant can be
+/// written with different precision or offsets, so compare the converted
+/// datetimes instead.
+///
+/// `Timestamp` converts into `serde_json::Value`, so it can be passed directly
+/// to `Filter` constructors and inserted into rows:
+///
+/// ```ignore
+/// let since = Timestamp::try_from(chrono::Utc::now() - chrono::Duration::days(7))?;
+/// let filter = Filter::gte("created_at", since);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Timestamp {
+    raw: String,
+    #[cfg(feature = "chrono")]
+    chrono: Option<chrono::DateTime<chrono::Utc>>,
+    #[cfg(feature = "time")]
+    time: Option<time::OffsetDateTime>,
+}
+
+impl Serialize for Timestamp {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.raw)
+    }
+}
+
+impl<'de> Deserialize<'de> for Timestamp {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let ts = Timestamp::new(String::deserialize(deserializer)?);
+        #[cfg(feature = "chrono")]
+        ts.to_chrono().map_err(serde::de::Error::custom)?;
+        #[cfg(feature = "time")]
+        ts.to_offset_date_time().map_err(serde::de::Error::custom)?;
+        Ok(ts)
+    }
+}
+
+impl Timestamp {
+    pub fn new(value: impl Into<String>) -> Self {
+        let raw = value.into();
+        Timestamp {
+            #[cfg(feature = "chrono")]
+            chrono: chrono_impls::parse(&raw),
+            #[cfg(feature = "time")]
+            time: time_impls::parse(&raw),
+            raw,
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.raw
+    }
+
+    pub fn into_string(self) -> String {
+        self.raw
+    }
+}
+
+/// Seconds since the Unix epoch and nanoseconds of an RFC 3339 datetime with
+/// any offset, keeping up to nanosecond precision. Values are comparable as
+/// tuples, so two spellings of the same instant compare equal.
+pub(crate) fn parse_rfc3339(s: &str) -> Option<(i64, u32)> {
+    let b = s.as_bytes();
+    let num = |range: std::ops::Range<usize>| -> Option<i64> {
+        let digits = s.get(range)?;
+        digits.bytes().all(|c| c.is_ascii_digit()).then(|| digits.parse().ok())?
+    };
+    if b.len() < 20 || b[4] != b'-' || b[7] != b'-' || !matches!(b[10], b'T' | b't' | b' ') || b[13] != b':' || b[16] != b':' {
+        return None;
+    }
+    let (year, month, day) = (num(0..4)?, num(5..7)?, num(8..10)?);
+    let (hour, minute, second) = (num(11..13)?, num(14..16)?, num(17..19)?);
+    let leap = year % 4 == 0 && (year % 100 != 0 || year % 400 == 0);
+    let month_days = match month {
+        2 if leap => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    };
+    if !(1..=12).contains(&month) || !(1..=month_days).contains(&day) || hour > 23 || minute > 59 || second > 60 {
+        return None;
+    }
+
+    let mut i = 19;
+    let mut nanos = 0;
+    if b[i] == b'.' {
+        let start = i + 1;
+        i = start;
+        while i < b.len() && b[i].is_ascii_digit() {
+            i += 1;
+        }
+        if i == start {
+            return None;
+        }
+        let fraction = &s[start..i.min(start + 9)];
+        nanos = fraction.parse::<u32>().ok()? * 10u32.pow(9 - fraction.len() as u32);
+    }
+    let offset_minutes = match &s[i..] {
+        "Z" | "z" => 0,
+        rest if rest.len() == 6 && rest.as_bytes()[3] == b':' => {
+            let sign = match rest.as_bytes()[0] {
+                b'+' => 1,
+                b'-' => -1,
+                _ => return None,
+            };
+            sign * (num(i + 1..i + 3)? * 60 + num(i + 4..i + 6)?)
+        }
+        _ => return None,
+    };
+
+    // Days since the epoch for a proleptic Gregorian date (Howard Hinnant's
+    // days_from_civil).
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let doy = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146097 + doe - 719468;
+
+    let seconds = days * 86400 + hour * 3600 + minute * 60 + second - offset_minutes * 60;
+    Some((seconds, nanos))
+}
+
+impl std::fmt::Display for Timestamp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.raw)
+    }
+}
+
+impl From<Timestamp> for serde_json::Value {
+    fn from(v: Timestamp) -> Self {
+        serde_json::Value::String(v.raw)
+    }
+}
+
+#[cfg(feature = "chrono")]
+mod chrono_impls {
+    use super::Timestamp;
+    use crate::Error;
+    use chrono::{Datelike, DateTime, SecondsFormat, TimeZone, Utc};
+
+    pub(super) fn parse(s: &str) -> Option<DateTime<Utc>> {
+        DateTime::parse_from_rfc3339(s).ok().map(|dt| dt.with_timezone(&Utc))
+    }
+
+    impl Timestamp {
+        /// The timestamp as a `chrono::DateTime<Utc>`.
+        pub fn to_chrono(&self) -> crate::Result<DateTime<Utc>> {
+            self.chrono.ok_or_else(|| Error::InvalidTimestamp(self.raw.clone()))
+        }
+    }
+
+    /// Fails for datetimes RFC 3339 can't represent, such as years past 9999.
+    impl<Tz: TimeZone> TryFrom<DateTime<Tz>> for Timestamp {
+        type Error = Error;
+
+        fn try_from(v: DateTime<Tz>) -> crate::Result<Self> {
+            let utc = v.with_timezone(&Utc);
+            if !(0..=9999).contains(&utc.year()) {
+                return Err(Error::InvalidTimestamp(format!("{} is outside RFC 3339's year range", utc)));
+            }
+            Ok(Timestamp::new(utc.to_rfc3339_opts(SecondsFormat::Millis, true)))
+        }
+    }
+
+    impl TryFrom<Timestamp> for DateTime<Utc> {
+        type Error = Error;
+
+        fn try_from(v: Timestamp) -> crate::Result<Self> {
+            v.to_chrono()
+        }
+    }
+}
+
+#[cfg(feature = "time")]
+mod time_impls {
+    use super::Timestamp;
+    use crate::Error;
+    use time::format_description::well_known::Rfc3339;
+    use time::macros::format_description;
+    use time::{OffsetDateTime, UtcOffset};
+
+    pub(super) fn parse(s: &str) -> Option<OffsetDateTime> {
+        OffsetDateTime::parse(s, &Rfc3339).ok().map(|dt| dt.to_offset(UtcOffset::UTC))
+    }
+
+    impl Timestamp {
+        /// The timestamp as a `time::OffsetDateTime` in UTC.
+        pub fn to_offset_date_time(&self) -> crate::Result<OffsetDateTime> {
+            self.time.ok_or_else(|| Error::InvalidTimestamp(self.raw.clone()))
+        }
+    }
+
+    /// Fails for datetimes RFC 3339 can't represent, such as years past 9999.
+    impl TryFrom<OffsetDateTime> for Timestamp {
+        type Error = Error;
+
+        fn try_from(v: OffsetDateTime) -> crate::Result<Self> {
+            let format = format_description!(
+                "[year]-[month]-[day]T[hour]:[minute]:[second].[subsecond digits:3]Z"
+            );
+            let utc = v.to_offset(UtcOffset::UTC);
+            if !(0..=9999).contains(&utc.year()) {
+                return Err(Error::InvalidTimestamp(format!("{} is outside RFC 3339's year range", utc)));
+            }
+            utc.format(&format)
+                .map(Timestamp::new)
+                .map_err(|e| Error::InvalidTimestamp(format!("{}: {}", utc, e)))
+        }
+    }
+
+    impl TryFrom<Timestamp> for OffsetDateTime {
+        type Error = Error;
+
+        fn try_from(v: Timestamp) -> crate::Result<Self> {
+            v.to_offset_date_time()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timestamp_into_value() {
+        let ts = Timestamp::new("2024-01-15T12:00:00.000Z");
+        let value: serde_json::Value = ts.into();
+        assert_eq!(value, serde_json::json!("2024-01-15T12:00:00.000Z"));
+    }
+
+    #[test]
+    fn test_parse_rfc3339() {
+        assert_eq!(parse_rfc3339("1970-01-01T00:00:00Z"), Some((0, 0)));
+        assert_eq!(parse_rfc3339("2024-01-15T12:00:00.250Z"), Some((1_705_320_000, 250_000_000)));
+        assert_eq!(parse_rfc3339("2024-01-15T14:00:00.25+02:00"), Some((1_705_320_000, 250_000_000)));
+        assert_eq!(parse_rfc3339("1969-12-31T23:59:59.9999999999Z"), Some((-1, 999_999_999)));
+        assert_eq!(parse_rfc3339("2024-01-15"), None);
+        assert_eq!(parse_rfc3339("2024-13-15T12:00:00Z"), None);
+    }
+
+    #[test]
+    fn test_parse_rfc3339_checks_month_length() {
+        assert!(parse_rfc3339("2024-02-29T00:00:00Z").is_some());
+        assert!(parse_rfc3339("2000-02-29T00:00:00Z").is_some());
+        assert!(parse_rfc3339("2024-12-31T00:00:00Z").is_some());
+        assert_eq!(parse_rfc3339("2023-02-29T00:00:00Z"), None);
+        assert_eq!(parse_rfc3339("1900-02-29T00:00:00Z"), None);
+        assert_eq!(parse_rfc3339("2024-02-31T00:00:00Z"), None);
+        assert_eq!(parse_rfc3339("2024-04-31T00:00:00Z"), None);
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn test_chrono_round_trip() {
+        use chrono::{FixedOffset, TimeZone, Utc};
+
+        let dt = FixedOffset::east_opt(2 * 3600)
+            .unwrap()
+            .with_ymd_and_hms(2024, 1, 15, 14, 0, 0)
+            .unwrap();
+        let ts = Timestamp::try_from(dt).unwrap();
+        assert_eq!(ts.as_str(), "2024-01-15T12:00:00.000Z");
+        assert_eq!(ts.to_chrono().unwrap(), Utc.with_ymd_and_hms(2024, 1, 15, 12, 0, 0).unwrap());
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn test_chrono_invalid() {
+        use chrono::{TimeZone, Utc};
+
+        assert!(Timestamp::new("yesterday").to_chrono().is_err());
+        let far = Utc.with_ymd_and_hms(10_000, 1, 1, 0, 0, 0).unwrap();
+        assert!(matches!(Timestamp::try_from(far), Err(crate::Error::InvalidTimestamp(_))));
+    }
+
+    #[cfg(feature = "time")]
+    #[test]
+    fn test_time_round_trip() {
+        use time::macros::datetime;
+
+        let dt = datetime!(2024-01-15 14:00:00.5 +02:00);
+        let ts = Timestamp::try_from(dt).unwrap();
+        assert_eq!(ts.as_str(), "2024-01-15T12:00:00.500Z");
+        assert_eq!(ts.to_offset_date_time().unwrap(), datetime!(2024-01-15 12:00:00.5 UTC));
+    }
+
+    #[cfg(feature = "time")]
+    #[test]
+    fn test_time_out_of_range() {
+        use time::macros::datetime;
+
+        assert!(Timestamp::try_from(datetime!(-0001-01-01 0:00 UTC)).is_err());
+    }
+
+    #[cfg(any(feature = "chrono", feature = "time"))]
+    #[test]
+    fn test_deserialize_parses() {
+        let ts: Timestamp = serde_json::from_str(r#""2024-01-15T14:00:00.5+02:00""#).unwrap();
+        assert_eq!(ts.as_str(), "2024-01-15T14:00:00.5+02:00");
+        assert!(serde_json::from_str::<Timestamp>(r#""yesterday""#).is_err());
+        assert!(serde_json::from_str::<crate::NamespaceMetadata>(r#"{"created_at": "yesterday"}"#).is_err());
+    }
+}
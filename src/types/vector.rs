@@ -0,0 +1,385 @@
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+
+use crate::Error;
+
+/// An element type of a packed vector, encoded as `WIDTH` little-endian
+/// bytes.
+pub(crate) trait Element: Copy {
+    const WIDTH: usize;
+
+    fn write_le(self, out: &mut Vec<u8>);
+
+    fn read_le(bytes: &[u8]) -> Self;
+
+    fn from_f64(v: f64) -> Self;
+}
+
+impl Element for f32 {
+    const WIDTH: usize = 4;
+
+    fn write_le(self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.to_le_bytes());
+    }
+
+    fn read_le(bytes: &[u8]) -> Self {
+        f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+    }
+
+    fn from_f64(v: f64) -> Self {
+        v as f32
+    }
+}
+
+#[cfg(feature = "half")]
+impl Element for half::f16 {
+    const WIDTH: usize = 2;
+
+    fn write_le(self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.to_le_bytes());
+    }
+
+    fn read_le(bytes: &[u8]) -> Self {
+        half::f16::from_le_bytes([bytes[0], bytes[1]])
+    }
+
+    fn from_f64(v: f64) -> Self {
+        half::f16::from_f64(v)
+    }
+}
+
+pub(crate) fn encode_base64<T: Element>(values: &[T]) -> String {
+    let mut bytes = Vec::with_capacity(values.len() * T::WIDTH);
+    for v in values {
+        v.write_le(&mut bytes);
+    }
+    STANDARD.encode(bytes)
+}
+
+/// Decode a base64 payload of packed little-endian elements.
+pub(crate) fn decode_base64<T: Element>(encoded: &str) -> crate::Result<Vec<T>> {
+    let bytes = STANDARD
+        .decode(encoded)
+        .map_err(|e| Error::InvalidVector(e.to_string()))?;
+    if bytes.len() % T::WIDTH != 0 {
+        return Err(Error::InvalidVector(format!(
+            "base64 payload of {} bytes is not a multiple of {}",
+            bytes.len(),
+            T::WIDTH
+        )));
+    }
+    Ok(bytes.chunks_exact(T::WIDTH).map(T::read_le).collect())
+}
+
+/// Elements from a row value: a base64 string or an array of numbers.
+pub(crate) fn decode_value<T: Element>(v: &serde_json::Value) -> crate::Result<Vec<T>> {
+    match v {
+        serde_json::Value::String(s) => decode_base64(s),
+        serde_json::Value::Array(items) => items
+            .iter()
+            .map(|item| {
+                item.as_f64()
+                    .map(T::from_f64)
+                    .ok_or_else(|| Error::InvalidVector(format!("non-numeric element {}", item)))
+            })
+            .collect(),
+        other => Err(Error::InvalidVector(format!("expected base64 string or array, got {}", other))),
+    }
+}
+
+/// Deserializes elements from a base64 string or an array of floats.
+pub(crate) struct ElementsVisitor<T>(pub(crate) std::marker::PhantomData<T>);
+
+impl<'de, T: Element> serde::de::Visitor<'de> for ElementsVisitor<T> {
+    type Value = Vec<T>;
+
+    fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str("a base64 string or an array of floats")
+    }
+
+    fn visit_str<E: serde::de::Error>(self, v: &str) -> Result<Vec<T>, E> {
+        decode_base64(v).map_err(E::custom)
+    }
+
+    fn visit_seq<A: serde::de::SeqAccess<'de>>(self, mut seq: A) -> Result<Vec<T>, A::Error> {
+        let mut values = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Some(v) = seq.next_element::<f64>()? {
+            values.push(T::from_f64(v));
+        }
+        Ok(values)
+    }
+}
+
+/// The shared API of a vector attribute type wrapping `Vec<$elem>`: base64
+/// serialization, deserialization from base64 or floats, and conversions.
+macro_rules! packed_vector {
+    ($name:ident, $elem:ty) => {
+        impl $name {
+            pub fn new(values: Vec<$elem>) -> Self {
+                $name(values)
+            }
+
+            pub fn as_slice(&self) -> &[$elem] {
+                &self.0
+            }
+
+            pub fn into_vec(self) -> Vec<$elem> {
+                self.0
+            }
+
+            pub fn len(&self) -> usize {
+                self.0.len()
+            }
+
+            pub fn is_empty(&self) -> bool {
+                self.0.is_empty()
+            }
+
+            #[doc = concat!("Encode as base64 of packed little-endian ", stringify!($elem), "s.")]
+            pub fn to_base64(&self) -> String {
+                $crate::types::vector::encode_base64(&self.0)
+            }
+
+            #[doc = concat!("Decode from base64 of packed little-endian ", stringify!($elem), "s.")]
+            pub fn from_base64(encoded: &str) -> $crate::Result<Self> {
+                $crate::types::vector::decode_base64(encoded).map($name)
+            }
+        }
+
+        impl std::ops::Deref for $name {
+            type Target = [$elem];
+
+            fn deref(&self) -> &[$elem] {
+                &self.0
+            }
+        }
+
+        impl From<Vec<$elem>> for $name {
+            fn from(v: Vec<$elem>) -> Self {
+                $name(v)
+            }
+        }
+
+        impl From<&[$elem]> for $name {
+            fn from(v: &[$elem]) -> Self {
+                $name(v.to_vec())
+            }
+        }
+
+        impl From<$name> for Vec<$elem> {
+            fn from(v: $name) -> Self {
+                v.0
+            }
+        }
+
+        impl From<$name> for serde_json::Value {
+            fn from(v: $name) -> Self {
+                serde_json::Value::String(v.to_base64())
+            }
+        }
+
+        impl TryFrom<&serde_json::Value> for $name {
+            type Error = $crate::Error;
+
+            fn try_from(v: &serde_json::Value) -> $crate::Result<Self> {
+                $crate::types::vector::decode_value(v).map($name)
+            }
+        }
+
+        impl TryFrom<serde_json::Value> for $name {
+            type Error = $crate::Error;
+
+            fn try_from(v: serde_json::Value) -> $crate::Result<Self> {
+                $name::try_from(&v)
+            }
+        }
+
+        impl serde::Serialize for $name {
+            fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+            where
+                S: serde::Serializer,
+            {
+                serializer.serialize_str(&self.to_base64())
+            }
+        }
+
+        impl<'de> serde::Deserialize<'de> for $name {
+            fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+            where
+                D: serde::Deserializer<'de>,
+            {
+                deserializer
+                    .deserialize_any($crate::types::vector::ElementsVisitor(std::marker::PhantomData))
+                    .map($name)
+            }
+        }
+    };
+}
+
+#[cfg(feature = "half")]
+pub(crate) use packed_vector;
+
+/// An f32 vector attribute.
+///
+/// Serializes as a base64 string of packed little-endian f32s, which is much
+/// smaller and faster to parse than a JSON float array. Deserializes from
+/// either a base64 string or a float array, so it works for query results
+/// regardless of the requested `VectorEncoding`.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Vector(Vec<f32>);
+
+packed_vector!(Vector, f32);
+
+#[cfg(feature = "ndarray")]
+mod ndarray_impls {
+    use super::Vector;
+    use ndarray::{Array1, ArrayView1, ArrayView2};
+
+    /// Take the elements of an owned array, reusing its buffer when the
+    /// array is contiguous.
+    pub(crate) fn array_into_vec(a: Array1<f32>) -> Vec<f32> {
+        if !a.is_standard_layout() {
+            return a.iter().copied().collect();
+        }
+        let len = a.len();
+        let (mut v, offset) = a.into_raw_vec_and_offset();
+        v.drain(..offset.unwrap_or(0));
+        v.truncate(len);
+        v
+    }
+
+    impl Vector {
+        /// One `Vector` per row of a batch of embeddings, each row copied.
+        pub fn from_rows(batch: ArrayView2<'_, f32>) -> Vec<Vector> {
+            batch.rows().into_iter().map(Vector::from).collect()
+        }
+
+        pub fn view(&self) -> ArrayView1<'_, f32> {
+            ArrayView1::from(self.as_slice())
+        }
+    }
+
+    impl From<Array1<f32>> for Vector {
+        fn from(v: Array1<f32>) -> Self {
+            Vector::new(array_into_vec(v))
+        }
+    }
+
+    impl From<ArrayView1<'_, f32>> for Vector {
+        fn from(v: ArrayView1<'_, f32>) -> Self {
+            Vector::new(v.to_vec())
+        }
+    }
+
+    impl From<Vector> for Array1<f32> {
+        fn from(v: Vector) -> Self {
+            Array1::from(v.into_vec())
+        }
+    }
+}
+
+#[cfg(feature = "ndarray")]
+pub(crate) use ndarray_impls::array_into_vec;
+
+#[cfg(feature = "nalgebra")]
+mod nalgebra_impls {
+    use super::Vector;
+    use nalgebra::{DVector, DVectorView};
+
+    impl From<DVector<f32>> for Vector {
+        fn from(v: DVector<f32>) -> Self {
+            Vector::new(v.data.into())
+        }
+    }
+
+    impl From<DVectorView<'_, f32>> for Vector {
+        fn from(v: DVectorView<'_, f32>) -> Self {
+            Vector::new(v.iter().copied().collect())
+        }
+    }
+
+    impl From<Vector> for DVector<f32> {
+        fn from(v: Vector) -> Self {
+            DVector::from_vec(v.into_vec())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_base64_serialization() {
+        let v = Vector::new(vec![1.0, -2.5]);
+        let json = serde_json::to_string(&v).unwrap();
+        assert_eq!(json, r#""AACAPwAAIMA=""#);
+    }
+
+    #[test]
+    fn test_deserialize_base64_and_floats() {
+        let from_b64: Vector = serde_json::from_str(r#""AACAPwAAIMA=""#).unwrap();
+        let from_floats: Vector = serde_json::from_str("[1.0, -2.5]").unwrap();
+        assert_eq!(from_b64.as_slice(), &[1.0, -2.5]);
+        assert_eq!(from_b64, from_floats);
+    }
+
+    #[test]
+    fn test_from_row_value() {
+        let row_value = serde_json::json!([0.5, 0.25]);
+        let v = Vector::try_from(&row_value).unwrap();
+        assert_eq!(v.into_vec(), vec![0.5, 0.25]);
+
+        let encoded: serde_json::Value = Vector::new(vec![0.5, 0.25]).into();
+        assert_eq!(Vector::try_from(encoded).unwrap().as_slice(), &[0.5, 0.25]);
+    }
+
+    #[test]
+    fn test_invalid_base64_length() {
+        assert!(Vector::from_base64("AAA=").is_err());
+        assert!(Vector::try_from(&serde_json::json!(true)).is_err());
+    }
+
+    #[cfg(feature = "half")]
+    #[test]
+    fn test_f16_encoding() {
+        use crate::F16Vector;
+
+        let v = F16Vector::from_f32_slice(&[1.0, -2.5]);
+        assert_eq!(serde_json::to_string(&v).unwrap(), r#""ADwAwQ==""#);
+        let from_floats: F16Vector = serde_json::from_str("[1.0, -2.5]").unwrap();
+        assert_eq!(serde_json::from_str::<F16Vector>(r#""ADwAwQ==""#).unwrap(), from_floats);
+        assert!(F16Vector::from_base64("AA==").is_err());
+    }
+
+    #[cfg(feature = "ndarray")]
+    #[test]
+    fn test_ndarray_conversions() {
+        use ndarray::{Array1, array, s};
+
+        let sliced = array![0.0, 1.0, 2.0, 3.0].slice_move(s![1..3]);
+        assert_eq!(Vector::from(sliced).as_slice(), &[1.0, 2.0]);
+
+        let strided = array![0.0, 1.0, 2.0, 3.0].slice_move(s![..;2]);
+        assert_eq!(Vector::from(strided).as_slice(), &[0.0, 2.0]);
+
+        let batch = array![[1.0, 2.0], [3.0, 4.0]];
+        let rows = Vector::from_rows(batch.view());
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[1].as_slice(), &[3.0, 4.0]);
+
+        let back: Array1<f32> = rows[0].clone().into();
+        assert_eq!(back, array![1.0, 2.0]);
+    }
+
+    #[cfg(feature = "nalgebra")]
+    #[test]
+    fn test_nalgebra_conversions() {
+        use nalgebra::DVector;
+
+        let v = Vector::from(DVector::from_vec(vec![1.0, 2.0]));
+        assert_eq!(v.as_slice(), &[1.0, 2.0]);
+        let back: DVector<f32> = v.into();
+        assert_eq!(back.as_slice(), &[1.0, 2.0]);
+    }
+}
@@ -1,6 +1,6 @@
 use rs_puff::{
-    Client, DistanceMetric, Filter, IncludeAttributes, NamespacesParams, QueryParams, RankBy,
-    WriteParams,
+    Client, DistanceMetric, Filter, Id, IncludeAttributes, NamespacesParams, QueryParams, RankBy,
+    Vector, VectorEncoding, WriteParams,
 };
 use serial_test::serial;
 use std::collections::HashMap;
@@ -124,12 +124,7 @@ async fn test_sanity() {
     assert_eq!(results2.rows[1].get("id").unwrap(), 1);
 
     // Delete one row
-    ns.write(WriteParams {
-        deletes: Some(vec![serde_json::json!(1)]),
-        ..Default::default()
-    })
-    .await
-    .unwrap();
+    ns.delete_ids([1u64]).await.unwrap();
 
     // Query again - should only get one result
     let results3 = ns
@@ -809,7 +804,7 @@ async fn test_empty_namespace_query() {
     .unwrap();
 
     ns.write(WriteParams {
-        deletes: Some(vec![serde_json::json!(1)]),
+        deletes: Some(vec![Id::Uint(1)]),
         ..Default::default()
     })
     .await
@@ -1021,6 +1016,46 @@ async fn test_contains_all_tokens() {
     ns.delete_all().await.unwrap();
 }
 
+#[tokio::test]
+#[serial]
+async fn test_base64_vectors() {
+    let client = setup();
+    let prefix = test_prefix();
+    let ns = client.namespace(format!("{}base64_vectors", prefix));
+
+    let _ = ns.delete_all().await;
+
+    let mut doc = HashMap::new();
+    doc.insert("id".to_string(), serde_json::json!(1));
+    doc.insert("vector".to_string(), Vector::new(vec![0.25, -0.5]).into());
+
+    ns.write(WriteParams {
+        upsert_rows: Some(vec![doc]),
+        distance_metric: Some(DistanceMetric::CosineDistance),
+        ..Default::default()
+    })
+    .await
+    .unwrap();
+
+    for encoding in [VectorEncoding::Float, VectorEncoding::Base64] {
+        let results = ns
+            .query(QueryParams {
+                rank_by: Some(RankBy::vector("vector", vec![0.25, -0.5])),
+                include_attributes: Some(IncludeAttributes::List(vec!["vector".to_string()])),
+                vector_encoding: Some(encoding),
+                top_k: Some(10),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+
+        let vector = Vector::try_from(results.rows[0].get("vector").unwrap()).unwrap();
+        assert_eq!(vector.as_slice(), &[0.25, -0.5]);
+    }
+
+    ns.delete_all().await.unwrap();
+}
+
 /// Cleanup test that deletes all ephemeral test namespaces with the `rust_sdk_` prefix.
 /// This helps clean up any orphaned namespaces from failed test runs.
 /// Marked as serial to run after all other tests complete.
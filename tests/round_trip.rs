@@ -0,0 +1,278 @@
+//! Property tests for serde round-trips of request types. These don't touch
+//! the network.
+
+use proptest::prelude::*;
+use rs_puff::{
+    AggregateBy, Bm25Params, Consistency, ConsistencyLevel, ContainsAllTokensParams, DistanceMetric,
+    Filter, Id, IncludeAttributes, MultiQueryParams, Order, PatchByFilter, QueryParams, QueryVector, RankBy,
+    VectorEncoding, WriteParams,
+};
+use serde_json::Value;
+use std::collections::HashMap;
+
+fn attr() -> impl Strategy<Value = String> {
+    prop_oneof![
+        "[a-z_][a-z0-9_]{0,8}",
+        // Names that collide with operators or need quoting.
+        Just("And".to_string()),
+        Just("not".to_string()),
+        "[ -~]{0,6}",
+    ]
+}
+
+fn float() -> impl Strategy<Value = f64> {
+    any::<f64>().prop_filter("JSON numbers are finite", |v| v.is_finite())
+}
+
+fn scalar() -> impl Strategy<Value = Value> {
+    prop_oneof![
+        Just(Value::Null),
+        any::<bool>().prop_map(Value::from),
+        any::<i64>().prop_map(Value::from),
+        any::<u64>().prop_map(Value::from),
+        float().prop_map(Value::from),
+        "[ -~]{0,8}".prop_map(Value::from),
+    ]
+}
+
+fn value() -> impl Strategy<Value = Value> {
+    prop_oneof![
+        4 => scalar(),
+        1 => prop::collection::vec(scalar(), 0..4).prop_map(Value::Array),
+    ]
+}
+
+fn values() -> impl Strategy<Value = Vec<Value>> {
+    prop::collection::vec(scalar(), 0..4)
+}
+
+fn leaf_filter() -> impl Strategy<Value = Filter> {
+    let pattern = "[ -~]{0,8}";
+    prop_oneof![
+        (attr(), value()).prop_map(|(a, v)| Filter::eq(a, v)),
+        (attr(), value()).prop_map(|(a, v)| Filter::not_eq(a, v)),
+        (attr(), value()).prop_map(|(a, v)| Filter::lt(a, v)),
+        (attr(), value()).prop_map(|(a, v)| Filter::lte(a, v)),
+        (attr(), value()).prop_map(|(a, v)| Filter::gt(a, v)),
+        (attr(), value()).prop_map(|(a, v)| Filter::gte(a, v)),
+        (attr(), value()).prop_map(|(a, v)| Filter::any_lt(a, v)),
+        (attr(), value()).prop_map(|(a, v)| Filter::any_lte(a, v)),
+        (attr(), value()).prop_map(|(a, v)| Filter::any_gt(a, v)),
+        (attr(), value()).prop_map(|(a, v)| Filter::any_gte(a, v)),
+        (attr(), values()).prop_map(|(a, v)| Filter::r#in(a, v)),
+        (attr(), values()).prop_map(|(a, v)| Filter::not_in(a, v)),
+        (attr(), value()).prop_map(|(a, v)| Filter::contains(a, v)),
+        (attr(), value()).prop_map(|(a, v)| Filter::not_contains(a, v)),
+        (attr(), values()).prop_map(|(a, v)| Filter::contains_any(a, v)),
+        (attr(), values()).prop_map(|(a, v)| Filter::not_contains_any(a, v)),
+        (attr(), pattern).prop_map(|(a, p)| Filter::glob(a, p)),
+        (attr(), pattern).prop_map(|(a, p)| Filter::not_glob(a, p)),
+        (attr(), pattern).prop_map(|(a, p)| Filter::iglob(a, p)),
+        (attr(), pattern).prop_map(|(a, p)| Filter::not_iglob(a, p)),
+        (attr(), pattern).prop_map(|(a, p)| Filter::regex(a, p)),
+        (attr(), pattern, prop::option::of(prop::option::of(any::<bool>()))).prop_map(|(a, v, params)| {
+            Filter::ContainsAllTokens {
+                attr: a,
+                value: v,
+                params: params.map(|last_as_prefix| ContainsAllTokensParams { last_as_prefix }),
+            }
+        }),
+        (attr(), pattern).prop_map(|(a, v)| Filter::contains_token_sequence(a, v)),
+    ]
+}
+
+fn filter() -> impl Strategy<Value = Filter> {
+    leaf_filter().prop_recursive(3, 24, 4, |inner| {
+        prop_oneof![
+            prop::collection::vec(inner.clone(), 0..4).prop_map(Filter::And),
+            prop::collection::vec(inner.clone(), 0..4).prop_map(Filter::Or),
+            inner.prop_map(|f| Filter::Not(Box::new(f))),
+        ]
+    })
+}
+
+fn query_vector() -> impl Strategy<Value = QueryVector> {
+    let f32s = prop::collection::vec(any::<f32>().prop_filter("finite", |v| v.is_finite()), 0..6)
+        .prop_map(QueryVector::F32);
+    #[cfg(feature = "half")]
+    let f32s = prop_oneof![
+        f32s,
+        prop::collection::vec(any::<u16>().prop_map(half::f16::from_bits).prop_filter("finite", |v| v.is_finite()), 0..6)
+            .prop_map(QueryVector::F16),
+    ];
+    f32s
+}
+
+fn rank_by() -> impl Strategy<Value = RankBy> {
+    let leaf = prop_oneof![
+        (attr(), query_vector()).prop_map(|(a, v)| RankBy::vector(a, v)),
+        (attr(), query_vector()).prop_map(|(a, v)| RankBy::vector_knn(a, v)),
+        (attr(), "[ -~]{0,8}", prop::option::of(prop::option::of(any::<bool>()))).prop_map(
+            |(a, q, params)| RankBy::Bm25 {
+                attr: a,
+                query: q,
+                params: params.map(|last_as_prefix| Bm25Params { last_as_prefix }),
+            }
+        ),
+        (attr(), prop_oneof![Just(Order::Asc), Just(Order::Desc)]).prop_map(|(a, o)| RankBy::attribute(a, o)),
+    ];
+    leaf.prop_recursive(3, 16, 4, |inner| {
+        prop_oneof![
+            prop::collection::vec(inner.clone(), 0..4).prop_map(RankBy::Sum),
+            prop::collection::vec(inner.clone(), 0..4).prop_map(RankBy::Max),
+            (float(), inner).prop_map(|(w, r)| RankBy::product(w, r)),
+        ]
+    })
+}
+
+fn aggregate_by() -> impl Strategy<Value = Option<HashMap<String, AggregateBy>>> {
+    prop::option::of(prop::collection::hash_map(
+        attr(),
+        prop_oneof![Just(AggregateBy::Count), attr().prop_map(AggregateBy::Sum)],
+        0..3,
+    ))
+}
+
+fn consistency() -> impl Strategy<Value = Option<Consistency>> {
+    prop::option::of(
+        prop_oneof![Just(ConsistencyLevel::Strong), Just(ConsistencyLevel::Eventual)]
+            .prop_map(|level| Consistency { level }),
+    )
+}
+
+fn query_params() -> impl Strategy<Value = QueryParams> {
+    (
+        prop::option::of(rank_by()),
+        prop::option::of(any::<u64>()),
+        prop::option::of(filter()),
+        prop::option::of(prop_oneof![
+            any::<bool>().prop_map(IncludeAttributes::All),
+            prop::collection::vec(attr(), 0..3).prop_map(IncludeAttributes::List),
+        ]),
+        prop::option::of(prop::collection::vec(attr(), 0..3)),
+        prop::option::of(prop_oneof![Just(VectorEncoding::Float), Just(VectorEncoding::Base64)]),
+        prop::option::of(prop_oneof![
+            Just(DistanceMetric::CosineDistance),
+            Just(DistanceMetric::EuclideanSquared)
+        ]),
+        consistency(),
+        aggregate_by(),
+        prop::option::of(prop::collection::vec(attr(), 0..3)),
+    )
+        .prop_map(
+            |(
+                rank_by,
+                top_k,
+                filters,
+                include_attributes,
+                exclude_attributes,
+                vector_encoding,
+                distance_metric,
+                consistency,
+                aggregate_by,
+                group_by,
+            )| QueryParams {
+                rank_by,
+                top_k,
+                filters,
+                include_attributes,
+                exclude_attributes,
+                vector_encoding,
+                distance_metric,
+                consistency,
+                aggregate_by,
+                group_by,
+            },
+        )
+}
+
+fn id() -> impl Strategy<Value = Id> {
+    prop_oneof![any::<u64>().prop_map(Id::from), "[a-z0-9-]{1,8}".prop_map(Id::from)]
+}
+
+fn row() -> impl Strategy<Value = HashMap<String, Value>> {
+    prop::collection::hash_map(attr(), value(), 0..4)
+}
+
+fn write_params() -> impl Strategy<Value = WriteParams> {
+    (
+        prop::option::of(prop::collection::vec(row(), 0..3)),
+        prop::option::of(prop::collection::hash_map(attr(), values(), 0..3)),
+        prop::option::of(prop::collection::vec(row(), 0..3)),
+        prop::option::of(prop::collection::vec(id(), 0..4)),
+        prop::option::of(filter()),
+        prop::option::of((filter(), row()).prop_map(|(filters, patch)| PatchByFilter { filters, patch })),
+        prop::option::of(filter()),
+        prop::option::of(any::<bool>()),
+        prop::option::of("[a-z_]{1,8}"),
+    )
+        .prop_map(
+            |(
+                upsert_rows,
+                upsert_columns,
+                patch_rows,
+                deletes,
+                delete_by_filter,
+                patch_by_filter,
+                upsert_condition,
+                disable_backpressure,
+                copy_from_namespace,
+            )| WriteParams {
+                upsert_rows,
+                upsert_columns,
+                patch_rows,
+                deletes,
+                delete_by_filter,
+                patch_by_filter,
+                upsert_condition,
+                disable_backpressure,
+                copy_from_namespace,
+                ..Default::default()
+            },
+        )
+}
+
+proptest! {
+    #[test]
+    fn filter_round_trip(f in filter()) {
+        let json = serde_json::to_string(&f).unwrap();
+        let back: Filter = serde_json::from_str(&json).unwrap();
+        prop_assert_eq!(back, f);
+    }
+
+    #[test]
+    fn filter_dsl_round_trip(f in filter()) {
+        let text = f.to_string();
+        let back = Filter::parse(&text).map_err(|e| TestCaseError::fail(format!("{}: {}", text, e)))?;
+        prop_assert_eq!(back, f);
+    }
+
+    #[test]
+    fn rank_by_round_trip(r in rank_by()) {
+        let json = serde_json::to_string(&r).unwrap();
+        let back: RankBy = serde_json::from_str(&json).unwrap();
+        prop_assert_eq!(back, r);
+    }
+
+    #[test]
+    fn query_params_round_trip(q in query_params()) {
+        let json = serde_json::to_string(&q).unwrap();
+        let back: QueryParams = serde_json::from_str(&json).unwrap();
+        prop_assert_eq!(back, q);
+    }
+
+    #[test]
+    fn multi_query_params_round_trip(queries in prop::collection::vec(query_params(), 0..3), consistency in consistency()) {
+        let params = MultiQueryParams { queries, vector_encoding: None, consistency };
+        let json = serde_json::to_string(&params).unwrap();
+        let back: MultiQueryParams = serde_json::from_str(&json).unwrap();
+        prop_assert_eq!(back, params);
+    }
+
+    #[test]
+    fn write_params_round_trip(w in write_params()) {
+        let json = serde_json::to_string(&w).unwrap();
+        let back: WriteParams = serde_json::from_str(&json).unwrap();
+        prop_assert_eq!(back, w);
+    }
+}